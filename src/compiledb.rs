@@ -0,0 +1,227 @@
+use super::parser::MacroOp;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// entry of compile_commands.json
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompileCommand {
+    /// working directory of the compilation
+    pub directory: PathBuf,
+    /// translation unit; relative to `directory` if not absolute
+    pub file: PathBuf,
+    /// compile command as a single shell-escaped string
+    #[serde(default)]
+    pub command: Option<String>,
+    /// compile command as a list of arguments
+    #[serde(default)]
+    pub arguments: Option<Vec<String>>,
+}
+
+/// preprocessor flags picked up from a compile command
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompileFlags {
+    /// macros defined with `-D` or undefined with `-U` in the order of the command
+    pub macros: Vec<MacroOp>,
+    /// include directories with `-I`, `-iquote` and `-isystem`
+    pub include_paths: Vec<PathBuf>,
+    /// headers included with `-include`
+    pub forced_includes: Vec<PathBuf>,
+}
+
+impl CompileCommand {
+    /// absolute path of the translation unit
+    ///
+    pub fn filepath(&self) -> PathBuf {
+        normalize(&self.directory.join(&self.file))
+    }
+
+    /// list of arguments of the compile command
+    ///
+    pub fn args(&self) -> Vec<String> {
+        match (&self.arguments, &self.command) {
+            (Some(arguments), _) => arguments.clone(),
+            (None, Some(command)) => shlex::split(command).unwrap_or_default(),
+            (None, None) => vec![],
+        }
+    }
+
+    /// preprocessor flags of the compile command; relative paths are resolved against `directory`
+    ///
+    pub fn flags(&self) -> CompileFlags {
+        let mut flags = CompileFlags::default();
+        let args = self.args();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            // option value is given either joined or as the next argument
+            let mut value_of = |opt: &str| -> Option<String> {
+                let joined = arg.strip_prefix(opt)?;
+                if joined.is_empty() {
+                    iter.next().cloned()
+                } else {
+                    Some(joined.to_string())
+                }
+            };
+            if arg == "-include" {
+                if let Some(header) = value_of("-include") {
+                    flags.forced_includes.push(self.resolve(&header));
+                }
+            } else if let Some(dir) = value_of("-isystem").or_else(|| value_of("-iquote")) {
+                flags.include_paths.push(self.resolve(&dir));
+            } else if let Some(dir) = value_of("-I") {
+                flags.include_paths.push(self.resolve(&dir));
+            } else if let Some(define) = value_of("-D") {
+                let (name, value) = define.split_once('=').unwrap_or((&define, "1"));
+                flags
+                    .macros
+                    .push(MacroOp::Define(name.to_string(), value.to_string()));
+            } else if let Some(name) = value_of("-U") {
+                flags.macros.push(MacroOp::Undefine(name));
+            }
+        }
+        flags
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        normalize(&self.directory.join(path))
+    }
+}
+
+/// compilation database loaded from compile_commands.json
+#[derive(Debug, Clone, Default)]
+pub struct CompileDb {
+    pub commands: Vec<CompileCommand>,
+}
+
+impl CompileDb {
+    /// load compile_commands.json; a directory containing it can be given as well
+    ///
+    pub fn load(path: &Path) -> Result<Self> {
+        let path = if path.is_dir() {
+            path.join("compile_commands.json")
+        } else {
+            path.to_path_buf()
+        };
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read file `{}`", path.display()))?;
+        let commands = serde_json::from_str(&text)
+            .with_context(|| format!("failed to parse file `{}`", path.display()))?;
+        Ok(Self { commands })
+    }
+
+    /// compile command of the given source file
+    ///
+    pub fn find(&self, source: &Path) -> Option<&CompileCommand> {
+        let source = absolute(source);
+        self.commands.iter().find(|c| c.filepath() == source)
+    }
+
+    /// list of translation units matching the glob pattern such as "src/**/*.c"; all if None
+    ///
+    pub fn files(&self, pattern: Option<&str>) -> Result<Vec<PathBuf>> {
+        let pattern = pattern
+            .map(|p| glob::Pattern::new(&absolute(Path::new(p)).to_string_lossy()))
+            .transpose()
+            .context("invalid glob pattern")?;
+        let mut result: Vec<PathBuf> = vec![];
+        for command in &self.commands {
+            let filepath = command.filepath();
+            if pattern.as_ref().is_none_or(|p| p.matches_path(&filepath))
+                && !result.contains(&filepath)
+            {
+                result.push(filepath);
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// absolute and normalized path based on the current directory
+///
+fn absolute(path: &Path) -> PathBuf {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    normalize(&path)
+}
+
+/// resolve "." and ".." components without touching the file system
+///
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            _ => result.push(component),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_DB: &str = r#"[
+  {
+    "directory": "/work/build",
+    "command": "/usr/bin/cc -DMOTOR_COUNT=2 -D USE_PWM -UNDEBUG -UTRACE -DTRACE=0 -I../inc -isystem /opt/sdk/include -include config.h -o motor.o -c ../src/motor.c",
+    "file": "../src/motor.c"
+  },
+  {
+    "directory": "/work/build",
+    "arguments": ["cc", "-I", "../inc", "-c", "/work/src/timer.c"],
+    "file": "/work/src/timer.c"
+  }
+]"#;
+
+    #[test]
+    fn test_flags() {
+        let db = CompileDb {
+            commands: serde_json::from_str(TEST_DB).unwrap(),
+        };
+        let command = db.find(Path::new("/work/src/motor.c")).unwrap();
+        let flags = command.flags();
+        assert_eq!(
+            flags.macros,
+            [
+                MacroOp::Define("MOTOR_COUNT".to_string(), "2".to_string()),
+                MacroOp::Define("USE_PWM".to_string(), "1".to_string()),
+                MacroOp::Undefine("NDEBUG".to_string()),
+                MacroOp::Undefine("TRACE".to_string()),
+                MacroOp::Define("TRACE".to_string(), "0".to_string())
+            ]
+        );
+        assert_eq!(
+            flags.include_paths,
+            [
+                PathBuf::from("/work/inc"),
+                PathBuf::from("/opt/sdk/include")
+            ]
+        );
+        assert_eq!(
+            flags.forced_includes,
+            [PathBuf::from("/work/build/config.h")]
+        );
+        let command = db.find(Path::new("/work/src/timer.c")).unwrap();
+        assert_eq!(command.flags().include_paths, [PathBuf::from("/work/inc")]);
+    }
+
+    #[test]
+    fn test_files() {
+        let db = CompileDb {
+            commands: serde_json::from_str(TEST_DB).unwrap(),
+        };
+        assert_eq!(db.files(None).unwrap().len(), 2);
+        assert_eq!(
+            db.files(Some("/work/src/t*.c")).unwrap(),
+            [PathBuf::from("/work/src/timer.c")]
+        );
+    }
+}
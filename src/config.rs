@@ -0,0 +1,271 @@
+use super::encoding::TextEncoding;
+use super::expr::DataModel;
+use super::extractor::Extractor;
+use super::filter::NamePattern;
+use super::parser::{Language, LsvMacro, Parser, SourceKind};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// name of the config file searched from the working directory up to the root
+pub const CONFIG_FILENAME: &str = "cyagen.toml";
+
+/// settings of a run given by "[profile.<name>]"; relative paths are resolved from the directory of the config file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// glob patterns of the source files; ex) ["src/**/*.c"]
+    pub sources: Vec<String>,
+    /// template directory; its files override the ones of the template set
+    pub temp_dir: Option<PathBuf>,
+    /// built-in template set; ex) "gtest"
+    pub template_set: Option<String>,
+    /// output directory; ex) "tst/test_@sourcename@"
+    pub output_dir: Option<PathBuf>,
+    /// output json file path
+    pub json_filepath: Option<PathBuf>,
+    /// template directory rendered once with all the sources as a project
+    pub project_temp_dir: Option<PathBuf>,
+    /// output directory for project templates
+    pub project_output_dir: Option<PathBuf>,
+    /// include directories to search headers for constants
+    pub include_dirs: Vec<PathBuf>,
+    /// macros defined before parsing; ex) ["DEBUG", "BUFFER_SIZE=64"]
+    pub defines: Vec<String>,
+    /// source or header
+    pub kind: Option<SourceKind>,
+    /// c or cpp
+    pub language: Option<Language>,
+    /// true if the sources are preprocessor output
+    pub preprocessed: Option<bool>,
+    /// true to keep elements of preprocessed sources originating from included files
+    pub all_origins: Option<bool>,
+    /// ilp32, lp64 or llp64
+    pub data_model: Option<DataModel>,
+    /// character encoding of the sources
+    pub encoding: Option<TextEncoding>,
+    /// compile_commands.json
+    pub compile_db: Option<PathBuf>,
+    /// glob pattern to select translation units of compile_commands.json
+    pub db_filter: Option<String>,
+    /// functions to keep
+    pub include_fn: Vec<NamePattern>,
+    /// functions to remove
+    pub exclude_fn: Vec<NamePattern>,
+    /// variables to keep
+    pub include_var: Vec<NamePattern>,
+    /// variables to remove
+    pub exclude_var: Vec<NamePattern>,
+    /// macros defining local static variables; ex) ["TESTABLE_STATIC(type, name, init)"]
+    pub lsv_macros: Vec<LsvMacro>,
+    /// data files given to templates by key; ex) { vectors = "test_vectors.yaml" }
+    pub data: BTreeMap<String, PathBuf>,
+    /// variables given to templates; ex) { owner = "motor-team" }
+    pub vars: BTreeMap<String, String>,
+    /// true to keep rendering the remaining templates after a template fails
+    pub keep_going: Option<bool>,
+}
+
+impl Profile {
+    /// (name, value) of the defines; the value is "1" if not given
+    ///
+    pub fn define_pairs(&self) -> Vec<(String, String)> {
+        self.defines
+            .iter()
+            .map(|define| {
+                let (name, value) = define.split_once('=').unwrap_or((define, "1"));
+                (name.trim().to_string(), value.trim().to_string())
+            })
+            .collect()
+    }
+
+    /// the profile with the relative paths resolved from the directory
+    ///
+    fn resolved(mut self, dir: &Path) -> Self {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = dir.join(&*path);
+            }
+        };
+        self.sources = self
+            .sources
+            .iter()
+            .map(|pattern| match Path::new(pattern).is_relative() {
+                true => dir.join(pattern).to_string_lossy().to_string(),
+                false => pattern.clone(),
+            })
+            .collect();
+        self.temp_dir.iter_mut().for_each(resolve);
+        self.output_dir.iter_mut().for_each(resolve);
+        self.json_filepath.iter_mut().for_each(resolve);
+        self.project_temp_dir.iter_mut().for_each(resolve);
+        self.project_output_dir.iter_mut().for_each(resolve);
+        self.compile_db.iter_mut().for_each(resolve);
+        self.include_dirs.iter_mut().for_each(resolve);
+        self.data.values_mut().for_each(resolve);
+        self
+    }
+}
+
+/// settings given by a config file such as cyagen.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// list of "[[extractors]]"
+    #[serde(default)]
+    pub extractors: Vec<Extractor>,
+    /// profile used when no profile is given [default: "default"]
+    #[serde(default)]
+    pub default_profile: Option<String>,
+    /// named profiles of "[profile.<name>]"
+    #[serde(default)]
+    pub profile: BTreeMap<String, Profile>,
+    /// directory of the config file
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+impl Config {
+    /// load a TOML config file; patterns of the extractors are validated
+    ///
+    /// # Example
+    ///
+    /// ```toml
+    /// default_profile = "gtest"
+    ///
+    /// [[extractors]]
+    /// name = "tasks"
+    /// pattern = 'REGISTER_TASK\(\s*(?<name>\w+)\s*,\s*(?<prio>\w+)\s*\)'
+    /// scope = "file"
+    ///
+    /// [profile.gtest]
+    /// sources = ["src/**/*.c"]
+    /// temp_dir = "templates/gtest"
+    /// output_dir = "tst/test_@sourcename@"
+    /// include_dirs = ["include"]
+    /// defines = ["UNIT_TEST"]
+    /// lsv_macros = ["TESTABLE_STATIC(type, name, init)"]
+    /// exclude_fn = ["main"]
+    /// vars = { owner = "motor-team", asil = "B" }
+    ///
+    /// [profile.docs]
+    /// sources = ["src/**/*.c"]
+    /// temp_dir = "templates/docs"
+    /// output_dir = "docs/api"
+    /// ```
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read file `{}`", path.display()))?;
+        let mut config: Config = toml::from_str(&text)
+            .with_context(|| format!("failed to parse file `{}`", path.display()))?;
+        let builtin_names = builtin_names();
+        for extractor in &config.extractors {
+            extractor.regex()?;
+            // the list would be hidden by the built-in one in templates
+            if builtin_names.contains(&extractor.name) {
+                bail!(
+                    "extractor `{}` of `{}` has the name of a built-in list; use another name",
+                    extractor.name,
+                    path.display()
+                );
+            }
+        }
+        config.dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        Ok(config)
+    }
+
+    /// path of "cyagen.toml" in the directory or the nearest parent directory
+    ///
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(CONFIG_FILENAME))
+            .find(|path| path.is_file())
+    }
+
+    /// profile of the name with the resolved paths; without the name, `default_profile` or "default" if defined,
+    /// otherwise empty settings
+    ///
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        let name = match name.or(self.default_profile.as_deref()) {
+            Some(name) => name,
+            None if self.profile.contains_key("default") => "default",
+            None => return Ok(Profile::default()),
+        };
+        let Some(profile) = self.profile.get(name) else {
+            let names: Vec<&str> = self.profile.keys().map(|k| k.as_str()).collect();
+            bail!(
+                "no profile `{}` in the config; available profiles are [{}]",
+                name,
+                names.join(", ")
+            );
+        };
+        Ok(profile.clone().resolved(&self.dir))
+    }
+}
+
+/// names of the model in templates such as "fncs" and "typedefs"
+///
+fn builtin_names() -> Vec<String> {
+    let model = serde_json::to_value(Parser::parse("")).unwrap();
+    model.as_object().unwrap().keys().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let mut config: Config = toml::from_str(
+            r#"
+default_profile = "gtest"
+
+[profile.gtest]
+sources = ["src/*.c", "/abs/main.c"]
+keep_going = true
+
+[profile.docs]
+temp_dir = "templates/docs"
+"#,
+        )
+        .unwrap();
+        config.dir = PathBuf::from("/work");
+        // default_profile is taken without a name
+        let profile = config.profile(None).unwrap();
+        assert_eq!(profile.sources, ["/work/src/*.c", "/abs/main.c"]);
+        assert_eq!(profile.keep_going, Some(true));
+        let profile = config.profile(Some("docs")).unwrap();
+        assert_eq!(
+            profile.temp_dir,
+            Some(PathBuf::from("/work/templates/docs"))
+        );
+        assert!(config.profile(Some("release")).is_err());
+        // empty settings without default_profile and a "default" profile
+        config.default_profile = None;
+        assert!(config.profile(None).unwrap().sources.is_empty());
+        assert!(toml::from_str::<Config>("[profile.a]\ntemp_dri = \"x\"").is_err());
+        assert!(toml::from_str::<Config>("defualt_profile = \"a\"").is_err());
+        assert!(Config::default().profile(None).unwrap().sources.is_empty());
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = std::env::temp_dir().join(format!("cyagen-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CONFIG_FILENAME);
+        fs::write(
+            &path,
+            "[[extractors]]\nname = \"tasks\"\npattern = 'TASK\\((?<name>\\w+)'\n",
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.extractors[0].name, "tasks");
+        assert_eq!(config.dir, dir);
+        fs::write(&path, "[[extractors]]\nname = \"fncs\"\npattern = 'TASK'\n").unwrap();
+        let error = Config::load(&path).unwrap_err().to_string();
+        assert!(error.contains("extractor `fncs`"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
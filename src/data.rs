@@ -0,0 +1,124 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// data file given to templates under a key; "key=path" such as "vectors=./test_vectors.yaml"
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFile {
+    /// name of the data in the template context; ex) "vectors"
+    pub key: String,
+    /// json, yaml or toml file
+    pub path: PathBuf,
+}
+
+/// variable given to templates; "key=value" such as "owner=motor-team"
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataVar {
+    /// name of the variable in the template context; ex) "owner"
+    pub key: String,
+    /// value as a string
+    pub value: String,
+}
+
+impl FromStr for DataFile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, path) = split_key(s)?;
+        Ok(DataFile {
+            key,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+impl FromStr for DataVar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = split_key(s)?;
+        Ok(DataVar { key, value })
+    }
+}
+
+fn split_key(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected `key=value` but `{}` given", s)),
+    }
+}
+
+/// parse data text in the format of the file extension; json, yaml, yml or toml
+///
+pub fn parse_data(text: &str, extension: &str) -> Result<Value> {
+    let value = match extension {
+        "json" => serde_json::from_str(text)?,
+        "yaml" | "yml" => serde_norway::from_str(text)?,
+        "toml" => toml::from_str(text)?,
+        _ => bail!(
+            "unsupported data format `{}`; use json, yaml or toml",
+            extension
+        ),
+    };
+    Ok(value)
+}
+
+/// load a data file in json, yaml or toml
+///
+pub fn load_data(path: &Path) -> Result<Value> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read file `{}`", path.display()))?;
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    parse_data(&text, extension)
+        .with_context(|| format!("failed to parse file `{}`", path.display()))
+}
+
+/// object of the user data by key; variables are given after the data files, and the later
+/// one of the same key replaces the former
+///
+/// # Example
+///
+/// ```
+/// let vars = vec!["owner=motor-team".parse().unwrap(), "asil=B".parse().unwrap()];
+/// let data = cyagen::user_data(&[], &vars).unwrap();
+/// assert_eq!(data["owner"], "motor-team");
+/// ```
+pub fn user_data(files: &[DataFile], vars: &[DataVar]) -> Result<Value> {
+    let mut data = Map::new();
+    for file in files {
+        data.insert(file.key.clone(), load_data(&file.path)?);
+    }
+    for var in vars {
+        data.insert(var.key.clone(), Value::String(var.value.clone()));
+    }
+    Ok(Value::Object(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data() {
+        let file: DataFile = "vectors=./data/vectors.yaml".parse().unwrap();
+        assert_eq!(file.key, "vectors");
+        assert_eq!(file.path, PathBuf::from("./data/vectors.yaml"));
+        let var: DataVar = "title=a=b".parse().unwrap();
+        assert_eq!(var.value, "a=b");
+        assert!("=value".parse::<DataVar>().is_err());
+        assert!("novalue".parse::<DataVar>().is_err());
+
+        let yaml = "setDir:\n  - { input: 1, expected: 0 }\n";
+        let value = parse_data(yaml, "yaml").unwrap();
+        assert_eq!(value["setDir"][0]["expected"], 0);
+        let value = parse_data("[project]\nname = \"motor\"\n", "toml").unwrap();
+        assert_eq!(value["project"]["name"], "motor");
+        let value = parse_data(r#"{"level": "ASIL-B"}"#, "json").unwrap();
+        assert_eq!(value["level"], "ASIL-B");
+        assert!(parse_data("", "ini").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+use anyhow::{Context, Result};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// character encoding to decode text files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TextEncoding {
+    /// detected by BOM; UTF-8 otherwise
+    #[default]
+    Auto,
+    /// given encoding; ex) "windows-1252", "latin1" or "shift_jis"
+    Fixed(&'static Encoding),
+}
+
+impl FromStr for TextEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(TextEncoding::Auto);
+        }
+        Encoding::for_label(s.as_bytes())
+            .map(TextEncoding::Fixed)
+            .ok_or_else(|| {
+                format!(
+                    "unknown encoding `{}`; ex) auto, utf-8, latin1 or shift_jis",
+                    s
+                )
+            })
+    }
+}
+
+impl TryFrom<String> for TextEncoding {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// line ending of a text file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+/// encoding and line ending of a text file to be kept when it is written back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextFormat {
+    /// character encoding
+    pub encoding: &'static Encoding,
+    /// true if the file starts with BOM
+    pub bom: bool,
+    /// line ending of the file
+    pub line_ending: LineEnding,
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            bom: false,
+            line_ending: LineEnding::Lf,
+        }
+    }
+}
+
+/// decoded text of a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedText {
+    /// text with "\n" line endings
+    pub text: String,
+    /// original format of the text
+    pub format: TextFormat,
+    /// true if malformed sequences are replaced with U+FFFD
+    pub had_errors: bool,
+}
+
+/// decode bytes of a text file; BOM takes precedence over the given encoding,
+/// and malformed sequences are replaced instead of failing
+///
+/// # Example
+///
+/// ```
+/// let decoded = cyagen::decode_text(b"/* caf\xe9 */\r\n", "latin1".parse().unwrap());
+/// assert_eq!(decoded.text, "/* café */\n");
+/// assert_eq!(decoded.format.line_ending, cyagen::LineEnding::CrLf);
+/// ```
+pub fn decode_text(bytes: &[u8], encoding: TextEncoding) -> DecodedText {
+    let (encoding, bom_len) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_len)) => (encoding, bom_len),
+        None => match encoding {
+            TextEncoding::Auto => (UTF_8, 0),
+            TextEncoding::Fixed(encoding) => (encoding, 0),
+        },
+    };
+    let (text, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+    let line_ending = if text.contains("\r\n") {
+        LineEnding::CrLf
+    } else {
+        LineEnding::Lf
+    };
+    DecodedText {
+        text: text.replace("\r\n", "\n"),
+        format: TextFormat {
+            encoding,
+            bom: bom_len > 0,
+            line_ending,
+        },
+        had_errors,
+    }
+}
+
+/// encode text with "\n" line endings into the given format;
+/// characters not representable in the encoding are written as numeric character references
+/// and the returned flag is true
+///
+pub fn encode_text(text: &str, format: &TextFormat) -> (Vec<u8>, bool) {
+    let text = match format.line_ending {
+        LineEnding::Lf => text.to_string(),
+        LineEnding::CrLf => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+    };
+    let mut bytes = vec![];
+    let mut had_errors = false;
+    if format.encoding == UTF_16LE || format.encoding == UTF_16BE {
+        // encoding_rs does not encode into UTF-16
+        if format.bom {
+            bytes.extend(encode_utf16("\u{feff}", format.encoding));
+        }
+        bytes.extend(encode_utf16(&text, format.encoding));
+    } else {
+        if format.bom && format.encoding == UTF_8 {
+            bytes.extend_from_slice(b"\xef\xbb\xbf");
+        }
+        let (encoded, _, unmappable) = format.encoding.encode(&text);
+        bytes.extend_from_slice(&encoded);
+        had_errors = unmappable;
+    }
+    (bytes, had_errors)
+}
+
+fn encode_utf16(text: &str, encoding: &'static Encoding) -> Vec<u8> {
+    text.encode_utf16()
+        .flat_map(|unit| {
+            if encoding == UTF_16LE {
+                unit.to_le_bytes()
+            } else {
+                unit.to_be_bytes()
+            }
+        })
+        .collect()
+}
+
+/// read and decode a text file; a warning is printed if malformed sequences are replaced
+///
+pub fn read_text(path: &Path, encoding: TextEncoding) -> Result<DecodedText> {
+    let bytes =
+        fs::read(path).with_context(|| format!("failed to read file `{}`", path.display()))?;
+    let decoded = decode_text(&bytes, encoding);
+    if decoded.had_errors {
+        eprintln!(
+            "warning: `{}` is not valid {}; malformed characters are replaced (see --encoding)",
+            path.display(),
+            decoded.format.encoding.name()
+        );
+    }
+    Ok(decoded)
+}
+
+/// encode and write a text file in the given format; a warning is printed if characters are not representable
+///
+pub fn write_text(path: &Path, text: &str, format: &TextFormat) -> Result<()> {
+    let (bytes, had_errors) = encode_text(text, format);
+    if had_errors {
+        eprintln!(
+            "warning: `{}` has characters not representable in {}; they are written as numeric character references",
+            path.display(),
+            format.encoding.name()
+        );
+    }
+    fs::write(path, bytes).with_context(|| format!("failed to write file `{}`", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+
+    #[test]
+    fn test_decode_text() {
+        // BOM is detected regardless of the given encoding
+        let decoded = decode_text(b"\xff\xfea\x00\r\x00\n\x00", TextEncoding::Fixed(SHIFT_JIS));
+        assert_eq!(decoded.text, "a\n");
+        assert_eq!(decoded.format.encoding, UTF_16LE);
+        assert!(decoded.format.bom);
+        // Latin-1 falls back to replacement characters when decoded as UTF-8
+        let decoded = decode_text(b"int a; /* \xb5s */", TextEncoding::Auto);
+        assert!(decoded.had_errors);
+        assert_eq!(decoded.text, "int a; /* \u{fffd}s */");
+        let decoded = decode_text(b"int a; /* \xb5s */", "cp1252".parse().unwrap());
+        assert!(!decoded.had_errors);
+        assert_eq!(decoded.text, "int a; /* µs */");
+        assert_eq!(decoded.format.encoding, WINDOWS_1252);
+    }
+
+    #[test]
+    fn test_encode_text() {
+        let bytes = b"// \x93\xfa\x96\x7b\r\nint a;\r\n";
+        let decoded = decode_text(bytes, "shift_jis".parse().unwrap());
+        assert_eq!(decoded.text, "// 日本\nint a;\n");
+        assert_eq!(
+            encode_text(&decoded.text, &decoded.format),
+            (bytes.to_vec(), false)
+        );
+        let decoded = decode_text(b"\xef\xbb\xbfint a;\n", TextEncoding::Auto);
+        assert_eq!(
+            encode_text(&decoded.text, &decoded.format).0,
+            b"\xef\xbb\xbfint a;\n"
+        );
+        let format = TextFormat {
+            encoding: UTF_16BE,
+            bom: true,
+            line_ending: LineEnding::CrLf,
+        };
+        assert_eq!(encode_text("a\n", &format).0, b"\xfe\xff\x00a\x00\r\x00\n");
+        // characters not representable in the encoding are reported
+        let format = TextFormat {
+            encoding: WINDOWS_1252,
+            ..TextFormat::default()
+        };
+        assert_eq!(
+            encode_text("// 日\n", &format),
+            (b"// &#26085;\n".to_vec(), true)
+        );
+        assert!("unknown".parse::<TextEncoding>().is_err());
+    }
+}
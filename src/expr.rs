@@ -0,0 +1,577 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// maximum depth of nested macro expansion while evaluating an expression
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+/// target data model used to evaluate `sizeof` of primitive types
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataModel {
+    /// int, long and pointer are 32 bits; typical for 32-bit embedded targets
+    #[default]
+    Ilp32,
+    /// long and pointer are 64 bits; typical for 64-bit Linux hosts
+    Lp64,
+    /// only pointer is 64 bits; typical for 64-bit Windows hosts
+    Llp64,
+}
+
+impl DataModel {
+    /// size in bytes of the given primitive data type; None if unknown
+    ///
+    pub fn sizeof(&self, dtype: &str) -> Option<i64> {
+        let dtype = dtype
+            .split_whitespace()
+            .filter(|w| !matches!(*w, "const" | "volatile" | "signed"))
+            .collect::<Vec<&str>>()
+            .join(" ");
+        if dtype.ends_with('*') {
+            return Some(self.pointer_size());
+        }
+        let long_size = match self {
+            DataModel::Lp64 => 8,
+            _ => 4,
+        };
+        let size = match dtype.trim_start_matches("unsigned").trim() {
+            "char" | "_Bool" | "bool" | "int8_t" | "uint8_t" => 1,
+            "short" | "short int" | "int16_t" | "uint16_t" => 2,
+            "" | "int" | "int32_t" | "uint32_t" | "float" => 4,
+            "long" | "long int" => long_size,
+            "long long" | "long long int" | "int64_t" | "uint64_t" | "double" => 8,
+            "long double" => 16,
+            "size_t" | "ssize_t" | "ptrdiff_t" | "intptr_t" | "uintptr_t" => self.pointer_size(),
+            _ => return None,
+        };
+        Some(size)
+    }
+
+    fn pointer_size(&self) -> i64 {
+        match self {
+            DataModel::Ilp32 => 4,
+            _ => 8,
+        }
+    }
+}
+
+impl FromStr for DataModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ilp32" => Ok(DataModel::Ilp32),
+            "lp64" => Ok(DataModel::Lp64),
+            "llp64" => Ok(DataModel::Llp64),
+            _ => Err(format!(
+                "unknown data model `{}`; expected ilp32, lp64 or llp64",
+                s
+            )),
+        }
+    }
+}
+
+/// table of object-like macros and enum constants to evaluate constant expressions
+#[derive(Debug, Clone, Default)]
+pub struct ConstTable {
+    /// data model used for `sizeof`
+    pub data_model: DataModel,
+    /// object-like macros; name -> replacement text
+    macros: HashMap<String, String>,
+    /// enum constants; name -> value
+    values: HashMap<String, i64>,
+}
+
+impl ConstTable {
+    pub fn new(data_model: DataModel) -> Self {
+        Self {
+            data_model,
+            ..Default::default()
+        }
+    }
+
+    /// register an object-like macro
+    ///
+    pub fn define(&mut self, name: &str, text: &str) {
+        self.values.remove(name);
+        self.macros
+            .insert(name.to_string(), text.trim().to_string());
+    }
+
+    /// remove a macro
+    ///
+    pub fn undefine(&mut self, name: &str) {
+        self.macros.remove(name);
+    }
+
+    /// register a constant with known value such as an enum constant
+    ///
+    pub fn set_value(&mut self, name: &str, value: i64) {
+        self.macros.remove(name);
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// value of the given identifier if it is a known constant
+    ///
+    pub fn value_of(&self, name: &str) -> Option<i64> {
+        self.lookup(name, 0)
+    }
+
+    /// true if the given identifier is a known macro or enum constant
+    ///
+    pub fn contains(&self, name: &str) -> bool {
+        self.macros.contains_key(name) || self.values.contains_key(name)
+    }
+
+    /// true if the given identifier is a constant with known value such as an enum constant
+    ///
+    pub fn is_value(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    /// collect `#define` values and enum constants from C source code
+    ///
+    pub fn collect(&mut self, code: &str) {
+        let code = code.replace("\\\r\n", " ").replace("\\\n", " ");
+        let re4define =
+            Regex::new(r"(?m)^[ \t]*#[ \t]*define[ \t]+(?<name>\w+)(?<fn>\()?(?<value>.*)$")
+                .unwrap();
+        let re4enum = Regex::new(r"\benum\b\s*\w*\s*\{(?<body>[^}]*)\}").unwrap();
+        let re4item = Regex::new(r"^\s*(?<name>\w+)\s*(?:=\s*(?<value>[\s\S]+?))?\s*$").unwrap();
+        // defines and enums are collected in order of appearance
+        let mut items: Vec<(usize, regex::Captures)> = re4define
+            .captures_iter(&code)
+            .map(|c| (c.get(0).unwrap().start(), c))
+            .chain(
+                re4enum
+                    .captures_iter(&code)
+                    .map(|c| (c.get(0).unwrap().start(), c)),
+            )
+            .collect();
+        items.sort_by_key(|(pos, _)| *pos);
+        for (_, cap) in items {
+            if let Some(body) = cap.name("body") {
+                let mut next = Some(0i64);
+                for item in body.as_str().split(',') {
+                    if let Some(item) = re4item.captures(item) {
+                        let name = item.name("name").unwrap().as_str();
+                        let value = match item.name("value") {
+                            Some(expr) => self.eval(expr.as_str()),
+                            None => next,
+                        };
+                        if let Some(value) = value {
+                            self.set_value(name, value);
+                        }
+                        next = value.map(|v| v.wrapping_add(1));
+                    }
+                }
+            } else if cap.name("fn").is_none() {
+                let name = cap.name("name").unwrap().as_str();
+                self.define(name, cap.name("value").unwrap().as_str());
+            }
+        }
+    }
+
+    /// evaluate the given constant expression; None if it cannot be resolved
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut table = cyagen::ConstTable::new(cyagen::DataModel::Ilp32);
+    /// table.collect("#define BUF_LEN 8\nenum { A, B, C };");
+    /// assert_eq!(table.eval("BUF_LEN * 2"), Some(16));
+    /// assert_eq!(table.eval("(C << 2) | sizeof(uint16_t)"), Some(10));
+    /// assert_eq!(table.eval("UNKNOWN + 1"), None);
+    /// ```
+    pub fn eval(&self, expr: &str) -> Option<i64> {
+        self.eval_with_depth(expr, 0)
+    }
+
+    fn eval_with_depth(&self, expr: &str, depth: usize) -> Option<i64> {
+        if depth > MAX_EXPANSION_DEPTH {
+            return None;
+        }
+        let tokens = tokenize(expr)?;
+        let mut eval = Evaluator {
+            table: self,
+            tokens: &tokens,
+            pos: 0,
+            depth,
+        };
+        let value = eval.ternary()?;
+        if eval.pos == tokens.len() {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn lookup(&self, name: &str, depth: usize) -> Option<i64> {
+        if let Some(value) = self.values.get(name) {
+            return Some(*value);
+        }
+        self.macros
+            .get(name)
+            .and_then(|text| self.eval_with_depth(text, depth + 1))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(i64),
+    Ident(String),
+    Punct(&'static str),
+}
+
+const PUNCTS: [&str; 24] = [
+    "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "&", "|",
+    "^", "~", "!", "?", ":", "(", ")",
+];
+
+/// split an expression into tokens; None if it contains unsupported characters
+///
+fn tokenize(expr: &str) -> Option<Vec<Token>> {
+    let mut tokens = vec![];
+    let chars: Vec<char> = expr.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().collect();
+            tokens.push(Token::Num(parse_int_literal(&literal)?));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c == '\'' {
+            let (value, len) = parse_char_literal(&chars[i..])?;
+            tokens.push(Token::Num(value));
+            i += len;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let punct = PUNCTS.iter().find(|p| rest.starts_with(*p))?;
+            tokens.push(Token::Punct(punct));
+            i += punct.len();
+        }
+    }
+    Some(tokens)
+}
+
+/// parse C integer literal such as "0x10U", "0b101", "017", or "10UL"
+///
+fn parse_int_literal(literal: &str) -> Option<i64> {
+    let digits = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+    let lower = digits.to_ascii_lowercase();
+    let value = if let Some(hex) = lower.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        u64::from_str_radix(bin, 2)
+    } else if lower.len() > 1 && lower.starts_with('0') {
+        u64::from_str_radix(&lower[1..], 8)
+    } else {
+        lower.parse::<u64>()
+    };
+    value.ok().map(|v| v as i64)
+}
+
+/// parse C character literal and return its value with the consumed length
+///
+fn parse_char_literal(chars: &[char]) -> Option<(i64, usize)> {
+    match chars {
+        ['\'', '\\', esc, '\'', ..] => {
+            let value = match esc {
+                'n' => '\n' as i64,
+                't' => '\t' as i64,
+                'r' => '\r' as i64,
+                '0' => 0,
+                '\\' => '\\' as i64,
+                '\'' => '\'' as i64,
+                '"' => '"' as i64,
+                _ => return None,
+            };
+            Some((value, 4))
+        }
+        ['\'', c, '\'', ..] => Some((*c as i64, 3)),
+        _ => None,
+    }
+}
+
+/// true if the identifier names a primitive type or a qualifier
+///
+fn is_type_word(word: &str) -> bool {
+    matches!(
+        word,
+        "unsigned"
+            | "signed"
+            | "char"
+            | "short"
+            | "int"
+            | "long"
+            | "float"
+            | "double"
+            | "const"
+            | "volatile"
+            | "_Bool"
+            | "bool"
+    ) || word.ends_with("_t")
+}
+
+/// recursive descent evaluator following C operator precedence
+struct Evaluator<'a> {
+    table: &'a ConstTable,
+    tokens: &'a [Token],
+    pos: usize,
+    depth: usize,
+}
+
+impl Evaluator<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, punct: &'static str) -> bool {
+        if self.peek() == Some(&Token::Punct(punct)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ternary(&mut self) -> Option<i64> {
+        let cond = self.binary(0)?;
+        if self.eat("?") {
+            let then = self.ternary()?;
+            if !self.eat(":") {
+                return None;
+            }
+            let other = self.ternary()?;
+            Some(if cond != 0 { then } else { other })
+        } else {
+            Some(cond)
+        }
+    }
+
+    fn binary(&mut self, min_prec: u8) -> Option<i64> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Punct(op)) = self.peek() {
+            let op = *op;
+            let prec = match op {
+                "||" => 1,
+                "&&" => 2,
+                "|" => 3,
+                "^" => 4,
+                "&" => 5,
+                "==" | "!=" => 6,
+                "<" | "<=" | ">" | ">=" => 7,
+                "<<" | ">>" => 8,
+                "+" | "-" => 9,
+                "*" | "/" | "%" => 10,
+                _ => break,
+            };
+            if prec < min_prec {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.binary(prec + 1)?;
+            lhs = match op {
+                "||" => ((lhs != 0) || (rhs != 0)) as i64,
+                "&&" => ((lhs != 0) && (rhs != 0)) as i64,
+                "|" => lhs | rhs,
+                "^" => lhs ^ rhs,
+                "&" => lhs & rhs,
+                "==" => (lhs == rhs) as i64,
+                "!=" => (lhs != rhs) as i64,
+                "<" => (lhs < rhs) as i64,
+                "<=" => (lhs <= rhs) as i64,
+                ">" => (lhs > rhs) as i64,
+                ">=" => (lhs >= rhs) as i64,
+                "<<" => lhs.checked_shl(u32::try_from(rhs).ok()?)?,
+                ">>" => lhs.checked_shr(u32::try_from(rhs).ok()?)?,
+                "+" => lhs.wrapping_add(rhs),
+                "-" => lhs.wrapping_sub(rhs),
+                "*" => lhs.wrapping_mul(rhs),
+                "/" => lhs.checked_div(rhs)?,
+                "%" => lhs.checked_rem(rhs)?,
+                _ => unreachable!(),
+            };
+        }
+        Some(lhs)
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        if self.eat("-") {
+            return self.unary().map(|v| v.wrapping_neg());
+        }
+        if self.eat("+") {
+            return self.unary();
+        }
+        if self.eat("~") {
+            return self.unary().map(|v| !v);
+        }
+        if self.eat("!") {
+            return self.unary().map(|v| (v == 0) as i64);
+        }
+        if let Some(Token::Ident(word)) = self.peek() {
+            if word == "sizeof" {
+                self.pos += 1;
+                if !self.eat("(") {
+                    return None;
+                }
+                let dtype = self.type_name()?;
+                if !self.eat(")") {
+                    return None;
+                }
+                return self.table.data_model.sizeof(&dtype);
+            }
+        }
+        if self.peek() == Some(&Token::Punct("(")) {
+            let start = self.pos;
+            self.pos += 1;
+            if let Some(dtype) = self.type_name() {
+                if self.eat(")") {
+                    let value = self.unary()?;
+                    return Some(self.cast(&dtype, value));
+                }
+            }
+            self.pos = start;
+        }
+        self.primary()
+    }
+
+    /// consume a type name such as "unsigned int" or "uint8_t *"
+    ///
+    fn type_name(&mut self) -> Option<String> {
+        let start = self.pos;
+        let mut words = vec![];
+        while let Some(Token::Ident(word)) = self.peek() {
+            if !is_type_word(word) || self.table.contains(word) {
+                break;
+            }
+            words.push(word.clone());
+            self.pos += 1;
+        }
+        while self.eat("*") {
+            words.push("*".to_string());
+        }
+        if words.is_empty() || self.peek() != Some(&Token::Punct(")")) {
+            self.pos = start;
+            return None;
+        }
+        Some(words.join(" "))
+    }
+
+    /// truncate the value to the width of an integer type
+    ///
+    fn cast(&self, dtype: &str, value: i64) -> i64 {
+        if dtype.ends_with('*') || dtype.contains("float") || dtype.contains("double") {
+            return value;
+        }
+        let bits = match self.table.data_model.sizeof(dtype) {
+            Some(size) if size < 8 => size * 8,
+            _ => return value,
+        };
+        let is_unsigned = dtype.contains("unsigned") || dtype.starts_with('u') || dtype == "bool";
+        let shift = 64 - bits;
+        if is_unsigned {
+            ((value as u64) << shift >> shift) as i64
+        } else {
+            (value << shift) >> shift
+        }
+    }
+
+    fn primary(&mut self) -> Option<i64> {
+        match self.peek()?.clone() {
+            Token::Num(value) => {
+                self.pos += 1;
+                Some(value)
+            }
+            Token::Ident(name) => {
+                self.pos += 1;
+                self.table.lookup(&name, self.depth)
+            }
+            Token::Punct("(") => {
+                self.pos += 1;
+                let value = self.ternary()?;
+                if self.eat(")") {
+                    Some(value)
+                } else {
+                    None
+                }
+            }
+            Token::Punct(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_arithmetic() {
+        let table = ConstTable::new(DataModel::Ilp32);
+        assert_eq!(table.eval("1 + 2 * 3"), Some(7));
+        assert_eq!(table.eval("(1 + 2) * 3"), Some(9));
+        assert_eq!(table.eval("0x10U >> 2 | 1 << 3"), Some(12));
+        assert_eq!(table.eval("~0 & 0xFF ^ 0x0F"), Some(0xF0));
+        assert_eq!(table.eval("10 % 4 == 2 ? 'A' : 0"), Some(65));
+        assert_eq!(table.eval("1 / 0"), None);
+        assert_eq!(table.eval("1.5"), None);
+    }
+
+    #[test]
+    fn test_eval_macros_and_enums() {
+        let mut table = ConstTable::new(DataModel::Ilp32);
+        table.collect(
+            "\
+#define BUF_LEN 16
+#define TOTAL_LEN (BUF_LEN * 2 + \\
+    HEADER_LEN)
+#define HEADER_LEN 4U
+#define MAX(a, b) ((a) > (b) ? (a) : (b))
+typedef enum
+{
+    Idle = 0,
+    Forward,
+    TurnLeft = Forward + 4,
+    TurnRight,
+    MaxDirection
+} Direction_t;
+",
+        );
+        assert_eq!(table.eval("BUF_LEN * 2"), Some(32));
+        assert_eq!(table.eval("TOTAL_LEN"), Some(36));
+        assert_eq!(table.eval("MaxDirection"), Some(7));
+        assert_eq!(table.eval("MAX(1, 2)"), None);
+    }
+
+    #[test]
+    fn test_eval_sizeof_and_casts() {
+        let ilp32 = ConstTable::new(DataModel::Ilp32);
+        let lp64 = ConstTable::new(DataModel::Lp64);
+        assert_eq!(ilp32.eval("sizeof(long)"), Some(4));
+        assert_eq!(lp64.eval("sizeof(long)"), Some(8));
+        assert_eq!(ilp32.eval("sizeof(uint16_t) * 3"), Some(6));
+        assert_eq!(ilp32.eval("sizeof(char *)"), Some(4));
+        assert_eq!(ilp32.eval("(uint8_t)300"), Some(44));
+        assert_eq!(ilp32.eval("(int8_t)0xFF"), Some(-1));
+        assert_eq!(ilp32.eval("(unsigned int)(-1) >> 31"), Some(1));
+    }
+
+    #[test]
+    fn test_recursive_macro() {
+        let mut table = ConstTable::new(DataModel::Ilp32);
+        table.collect("#define A B\n#define B A\n");
+        assert_eq!(table.eval("A"), None);
+    }
+}
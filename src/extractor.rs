@@ -0,0 +1,75 @@
+use super::parser::Span;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// range of text searched by an extractor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtractorScope {
+    /// whole file without comments
+    #[default]
+    File,
+    /// each function body
+    Function,
+}
+
+/// user defined regex to collect project specific patterns as a list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Extractor {
+    /// name of the list in the template context and json; ex) "dem_events"
+    pub name: String,
+    /// regex with named groups; ex) "DEM_EVENT\\((?<id>\\w+)\\s*,"
+    pub pattern: String,
+    /// file or function
+    #[serde(default)]
+    pub scope: ExtractorScope,
+}
+
+impl Extractor {
+    /// compiled pattern of the extractor
+    ///
+    pub fn regex(&self) -> Result<Regex> {
+        Regex::new(&self.pattern)
+            .with_context(|| format!("invalid pattern of extractor `{}`", self.name))
+    }
+}
+
+/// identifiers in the list of an extractor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Extraction {
+    /// captured string by regex; ex) "DEM_EVENT(DEM_MOTOR_STALL, 3)"
+    pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
+    /// range of the captured string in the source
+    pub span: Span,
+    /// function where the match is found; empty at file scope
+    pub func_name: String,
+    /// matched named groups; ex) {"id": "DEM_MOTOR_STALL", "severity": "3"}
+    pub groups: BTreeMap<String, String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extractor() {
+        let extractor = Extractor {
+            name: "waits".to_string(),
+            pattern: r"WAIT\((?<ms>\d+)\)".to_string(),
+            scope: ExtractorScope::Function,
+        };
+        assert!(extractor.regex().unwrap().is_match("WAIT(10);"));
+        let extractor = Extractor {
+            name: "broken".to_string(),
+            pattern: "(".to_string(),
+            scope: ExtractorScope::File,
+        };
+        assert!(extractor.regex().is_err());
+    }
+}
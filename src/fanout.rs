@@ -0,0 +1,149 @@
+use regex::Regex;
+use serde_json::Value;
+
+/// names of the elements bound in the context by the list of the model
+const FAN_OUT_LISTS: [(&str, &str); 5] = [
+    ("fnc", "fncs"),
+    ("var", "static_vars"),
+    ("typedef", "typedefs"),
+    ("callee", "callees"),
+    ("module", "modules"),
+];
+
+/// output filenames of a template filename with the element of each output; placeholders such as
+/// "@fnc.name@" render one output per element of the list with the element bound by its name, and
+/// a condition between brackets keeps the elements whose attribute is true, false with `!` or equal to a value
+/// - `fnc` : functions of **fncs**; ex) "test_@sourcename@_@fnc.name@.cc.njk"
+/// - `var` : local variables of **static_vars**; ex) "@var[!func_name].name@.h.njk" for file scope variables only
+/// - `typedef` : types of **typedefs**
+/// - `callee` : called functions of **callees**
+/// - `module` : parsed sources of **modules** of a project
+///
+/// filenames without placeholders are given as they are with no element
+///
+/// # Example
+///
+/// ```
+/// let parser = cyagen::Parser::parse("void setDir(int dir)\n{\n}\nstatic void stop(void)\n{\n}\n");
+/// let model = serde_json::to_value(&parser).unwrap();
+/// let outputs = cyagen::fan_out("test_@fnc[!is_local].name@.cc", &model);
+/// assert_eq!(outputs.len(), 1);
+/// assert_eq!(outputs[0].0, "test_setDir.cc");
+/// assert_eq!(outputs[0].1.as_ref().unwrap().0, "fnc");
+/// ```
+pub fn fan_out(filename: &str, model: &Value) -> Vec<(String, Option<(String, Value)>)> {
+    let re = Regex::new(
+        r"@(?<var>\w+)(?:\[(?<not>!)?(?<attr>[\w.]+)(?:=(?<value>[^\]@]*))?\])?\.(?<path>[\w.]+)@",
+    )
+    .unwrap();
+    let Some((var, list)) = re.captures_iter(filename).find_map(|cap| {
+        FAN_OUT_LISTS
+            .iter()
+            .find(|(var, _)| *var == &cap["var"])
+            .copied()
+    }) else {
+        return vec![(filename.to_string(), None)];
+    };
+    let elements = model[list]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let mut outputs = vec![];
+    for element in elements {
+        let mut keep = true;
+        let output = re.replace_all(filename, |cap: &regex::Captures| {
+            if &cap["var"] != var {
+                return cap[0].to_string();
+            }
+            if let Some(attr) = cap.name("attr") {
+                let found = attribute(element, attr.as_str());
+                let matched = match cap.name("value") {
+                    Some(value) => text_of(found) == value.as_str(),
+                    None => is_true(found),
+                };
+                keep &= matched != cap.name("not").is_some();
+            }
+            sanitize(&text_of(attribute(element, &cap["path"])))
+        });
+        if keep {
+            outputs.push((output.to_string(), Some((var.to_string(), element.clone()))));
+        }
+    }
+    outputs
+}
+
+/// attribute of the element by the path such as "body_span.start_line"
+///
+fn attribute<'a>(element: &'a Value, path: &str) -> &'a Value {
+    path.split('.').fold(element, |value, key| &value[key])
+}
+
+fn text_of(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+fn is_true(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+/// characters not allowed in filenames are replaced with underscores; ex) "Motor::run" -> "Motor__run"
+///
+fn sanitize(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_fan_out() {
+        let code = "\
+static int count = 0;
+void setDir(int dir)
+{
+    static int calls = 0;
+    stop();
+}
+static void stop(void)
+{
+}
+";
+        let model = serde_json::to_value(Parser::parse(code)).unwrap();
+        let names = |filename: &str| -> Vec<String> {
+            fan_out(filename, &model)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
+        };
+        assert_eq!(
+            names("test_@fnc.name@.cc"),
+            ["test_setDir.cc", "test_stop.cc"]
+        );
+        assert_eq!(names("@fnc[is_local].name@_@fnc.line@.cc"), ["stop_7.cc"]);
+        assert_eq!(names("@var[func_name=setDir].name@.h"), ["calls.h"]);
+        assert_eq!(names("@callee.name@.h"), ["stop.h"]);
+        assert_eq!(names("@typedef.line@.h"), Vec::<String>::new());
+        assert_eq!(names("@unknown.name@.h"), ["@unknown.name@.h"]);
+        let outputs = fan_out("test.cc", &model);
+        assert_eq!(outputs, [("test.cc".to_string(), None)]);
+        assert_eq!(sanitize("Motor::run"), "Motor__run");
+    }
+}
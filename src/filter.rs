@@ -0,0 +1,160 @@
+use super::parser::Parser;
+use regex::Regex;
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// pattern to match element names; glob such as "init*", or regex between slashes such as "/^Motor_/"
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub enum NamePattern {
+    /// glob matching the whole name; ex) "test_*"
+    Glob(glob::Pattern),
+    /// regex matching a part of the name; ex) "/^(main|.*_init)$/"
+    Regex(Regex),
+}
+
+impl FromStr for NamePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+            Some(re) => Regex::new(re)
+                .map(NamePattern::Regex)
+                .map_err(|e| format!("invalid regex `{}`: {}", re, e)),
+            None => glob::Pattern::new(s)
+                .map(NamePattern::Glob)
+                .map_err(|e| format!("invalid glob `{}`: {}", s, e)),
+        }
+    }
+}
+
+impl TryFrom<String> for NamePattern {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl NamePattern {
+    /// true if the name matches the pattern
+    ///
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Glob(pattern) => pattern.matches(name),
+            NamePattern::Regex(re) => re.is_match(name),
+        }
+    }
+}
+
+/// patterns to select functions and variables; an element is kept if it matches any of
+/// the include patterns (or no include pattern is given) and none of the exclude patterns
+#[derive(Debug, Clone, Default)]
+pub struct FilterSpec {
+    /// functions to keep; ex) ["Motor_*"]
+    pub include_fns: Vec<NamePattern>,
+    /// functions to remove; ex) ["main", "*_init"]
+    pub exclude_fns: Vec<NamePattern>,
+    /// variables to keep
+    pub include_vars: Vec<NamePattern>,
+    /// variables to remove; ex) ["/^gen_/"]
+    pub exclude_vars: Vec<NamePattern>,
+}
+
+impl FilterSpec {
+    /// true if no pattern is given
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.include_fns.is_empty()
+            && self.exclude_fns.is_empty()
+            && self.include_vars.is_empty()
+            && self.exclude_vars.is_empty()
+    }
+
+    /// true if the function is selected
+    ///
+    pub fn keeps_fn(&self, name: &str) -> bool {
+        keeps(&self.include_fns, &self.exclude_fns, name)
+    }
+
+    /// true if the variable is selected
+    ///
+    pub fn keeps_var(&self, name: &str) -> bool {
+        keeps(&self.include_vars, &self.exclude_vars, name)
+    }
+}
+
+fn keeps(include: &[NamePattern], exclude: &[NamePattern], name: &str) -> bool {
+    (include.is_empty() || include.iter().any(|p| p.matches(name)))
+        && !exclude.iter().any(|p| p.matches(name))
+}
+
+impl Parser {
+    /// remove the functions and variables not selected by the filter; local static variables
+    /// and calls of removed functions are removed as well
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut parser = cyagen::Parser::parse("int main(void) { return run(); }\nint run(void) { return 0; }");
+    /// let spec = cyagen::FilterSpec {
+    ///     exclude_fns: vec!["main".parse().unwrap()],
+    ///     ..Default::default()
+    /// };
+    /// parser.filter(&spec);
+    /// assert_eq!(parser.fncs.len(), 1);
+    /// assert!(parser.ncls.is_empty());
+    /// ```
+    pub fn filter(&mut self, spec: &FilterSpec) {
+        self.retain(|name| spec.keeps_fn(name), |name| spec.keeps_var(name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let code = "\
+static int count = 0;
+static int gen_table[4];
+void Motor_init(void)
+{
+    static int initialized = 0;
+#pragma inline
+    count = 0;
+}
+void Motor_run(void)
+{
+    Motor_init();
+    Motor_stop();
+}
+void Motor_stop(void)
+{
+}
+int main(void)
+{
+    Motor_run();
+    return 0;
+}
+";
+        let mut parser = Parser::parse(code);
+        let spec = FilterSpec {
+            include_fns: vec!["Motor_*".parse().unwrap()],
+            exclude_fns: vec!["/_init$/".parse().unwrap()],
+            exclude_vars: vec!["gen_*".parse().unwrap()],
+            ..Default::default()
+        };
+        parser.filter(&spec);
+        let names: Vec<&str> = parser.fncs.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["Motor_run", "Motor_stop"]);
+        let names: Vec<&str> = parser.static_vars.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["count"]);
+        assert_eq!(parser.ncls.len(), 1);
+        assert_eq!(parser.ncls[0].callee.name, "Motor_stop");
+        assert!(parser.callees.iter().all(|f| f.name == "Motor_stop"));
+        assert!(parser.pragmas.is_empty());
+        assert!("/(/".parse::<NamePattern>().is_err());
+    }
+}
@@ -0,0 +1,560 @@
+use super::parser::Typedefs;
+use regex::Regex;
+use std::collections::HashMap;
+use tera::{Tera, Value};
+
+/// register the filters; `c_default_value` looks up the given types
+///
+pub(crate) fn register_filters(tera: &mut Tera, types: TypeTable) {
+    tera.register_filter("snake_case", string_filter("snake_case", snake_case));
+    tera.register_filter("camel_case", string_filter("camel_case", camel_case));
+    tera.register_filter("pascal_case", string_filter("pascal_case", pascal_case));
+    tera.register_filter("upper_snake", string_filter("upper_snake", upper_snake));
+    tera.register_filter(
+        "strip_qualifiers",
+        string_filter("strip_qualifiers", strip_qualifiers),
+    );
+    tera.register_filter("base_type", string_filter("base_type", base_type));
+    tera.register_filter("deref_type", string_filter("deref_type", deref_type));
+    tera.register_filter("c_escape", string_filter("c_escape", c_escape));
+    tera.register_filter(
+        "printf_format",
+        string_filter("printf_format", printf_format),
+    );
+    tera.register_filter(
+        "gmock_matcher",
+        string_filter("gmock_matcher", gmock_matcher),
+    );
+    tera.register_filter(
+        "is_pointer",
+        |value: &Value, _: &HashMap<String, Value>| -> tera::Result<Value> {
+            Ok(Value::Bool(is_pointer(as_str("is_pointer", value)?)))
+        },
+    );
+    tera.register_filter(
+        "c_default_value",
+        move |value: &Value, _: &HashMap<String, Value>| -> tera::Result<Value> {
+            let dtype = as_str("c_default_value", value)?;
+            Ok(Value::String(c_default_value(dtype, &types)))
+        },
+    );
+    tera.register_filter("align", align_filter);
+    tera.register_filter("column", column_filter);
+}
+
+fn as_str<'a>(filter: &str, value: &'a Value) -> tera::Result<&'a str> {
+    value
+        .as_str()
+        .ok_or_else(|| tera::Error::msg(format!("`{}` expects a string, got {}", filter, value)))
+}
+
+fn string_filter(
+    filter: &'static str,
+    f: fn(&str) -> String,
+) -> impl Fn(&Value, &HashMap<String, Value>) -> tera::Result<Value> + Send + Sync {
+    move |value, _| Ok(Value::String(f(as_str(filter, value)?)))
+}
+
+/// words of an identifier split by separators and case changes; ex) "HTTPServer_init2" -> ["HTTP", "Server", "init2"]
+///
+fn split_words(name: &str) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+    let chars: Vec<char> = name.chars().collect();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if let Some(&prev) = word.chars().last().as_ref() {
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            // "setDir" -> "set", "Dir"; "HTTPServer" -> "HTTP", "Server"
+            if c.is_uppercase()
+                && (prev.is_lowercase()
+                    || prev.is_ascii_digit()
+                    || (prev.is_uppercase() && next_is_lower))
+            {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or(String::new(), |first| {
+        first.to_uppercase().collect::<String>() + &chars.as_str().to_lowercase()
+    })
+}
+
+fn snake_case(name: &str) -> String {
+    split_words(name)
+        .iter()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<String>>()
+        .join("_")
+}
+
+fn upper_snake(name: &str) -> String {
+    snake_case(name).to_uppercase()
+}
+
+fn pascal_case(name: &str) -> String {
+    split_words(name).iter().map(|w| capitalize(w)).collect()
+}
+
+fn camel_case(name: &str) -> String {
+    let words = split_words(name);
+    let mut result = String::new();
+    for (i, word) in words.iter().enumerate() {
+        if i == 0 {
+            result.push_str(&word.to_lowercase());
+        } else {
+            result.push_str(&capitalize(word));
+        }
+    }
+    result
+}
+
+/// true if the word is a type qualifier or storage class
+///
+fn is_qualifier(word: &str) -> bool {
+    matches!(
+        word,
+        "const"
+            | "volatile"
+            | "restrict"
+            | "__restrict"
+            | "__restrict__"
+            | "static"
+            | "extern"
+            | "register"
+            | "inline"
+            | "__inline"
+    )
+}
+
+/// words and "*" of a data type without qualifiers; "[]" is taken as "*"
+///
+fn type_tokens(dtype: &str) -> Vec<String> {
+    dtype
+        .replace("[]", " * ")
+        .replace('*', " * ")
+        .split_whitespace()
+        .filter(|w| !is_qualifier(w))
+        .map(|w| w.to_string())
+        .collect()
+}
+
+/// join words with spaces and stars without; ex) ["char", "*", "*"] -> "char **"
+///
+fn join_tokens(tokens: &[String]) -> String {
+    let mut result = String::new();
+    for token in tokens {
+        if token == "*" {
+            if !result.ends_with('*') {
+                result.push(' ');
+            }
+            result.push('*');
+        } else {
+            if !result.is_empty() {
+                result.push(' ');
+            }
+            result.push_str(token);
+        }
+    }
+    result
+}
+
+fn strip_qualifiers(dtype: &str) -> String {
+    join_tokens(&type_tokens(dtype))
+}
+
+pub(crate) fn base_type(dtype: &str) -> String {
+    let tokens: Vec<String> = type_tokens(dtype)
+        .into_iter()
+        .filter(|t| t != "*")
+        .collect();
+    join_tokens(&tokens)
+}
+
+fn is_pointer(dtype: &str) -> bool {
+    dtype.contains('*') || dtype.contains("[]")
+}
+
+fn deref_type(dtype: &str) -> String {
+    let dtype = dtype.replace("[]", "*");
+    match dtype.rfind('*') {
+        Some(pos) => {
+            let tokens: Vec<String> = dtype
+                .get(..pos)
+                .unwrap()
+                .replace('*', " * ")
+                .split_whitespace()
+                .map(|w| w.to_string())
+                .collect();
+            join_tokens(&tokens)
+        }
+        None => dtype.trim().to_string(),
+    }
+}
+
+fn c_escape(text: &str) -> String {
+    let mut result = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '"' => result.push_str("\\\""),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if c.is_ascii_graphic() || c == ' ' => result.push(c),
+            // control and non-ASCII characters as octal escapes of UTF-8 bytes
+            c => {
+                let mut buf = [0u8; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    result.push_str(&format!("\\{:03o}", byte));
+                }
+            }
+        }
+    }
+    result
+}
+
+fn printf_format(dtype: &str) -> String {
+    let dtype = strip_qualifiers(dtype);
+    if let Some(format) = fixed_width_format(&dtype) {
+        return format;
+    }
+    let format = match dtype.as_str() {
+        "char *" | "signed char *" | "unsigned char *" => "%s",
+        t if t.ends_with('*') => "%p",
+        "char" => "%c",
+        "float" | "double" | "float32" | "float64" => "%f",
+        "long double" => "%Lf",
+        "size_t" => "%zu",
+        "long" | "long int" | "signed long" => "%ld",
+        "unsigned long" | "unsigned long int" => "%lu",
+        "long long" | "long long int" | "signed long long" => "%lld",
+        "unsigned long long" | "unsigned long long int" => "%llu",
+        "unsigned" | "unsigned int" | "unsigned short" | "unsigned char" => "%u",
+        _ => "%d",
+    };
+    format!("\"{}\"", format)
+}
+
+/// `"%" PRIu32` of "uint32_t" or "uint32", `"%" PRIdPTR` of "intptr_t"; None if not a fixed-width type
+///
+fn fixed_width_format(dtype: &str) -> Option<String> {
+    let (conversion, rest) = if let Some(rest) = dtype.strip_prefix("uint") {
+        ('u', rest)
+    } else if let Some(rest) = dtype.strip_prefix("sint") {
+        ('d', rest)
+    } else {
+        ('d', dtype.strip_prefix("int")?)
+    };
+    let width = match rest.strip_suffix("_t").unwrap_or(rest) {
+        width @ ("8" | "16" | "32" | "64") => width,
+        "ptr" => "PTR",
+        "max" => "MAX",
+        _ => return None,
+    };
+    Some(format!("\"%\" PRI{}{}", conversion, width))
+}
+
+fn gmock_matcher(dtype: &str) -> String {
+    let dtype = strip_qualifiers(dtype);
+    let matcher = match dtype.as_str() {
+        "char *" => "StrEq",
+        "float" | "float32" => "FloatEq",
+        "double" | "long double" | "float64" => "DoubleEq",
+        _ => "Eq",
+    };
+    matcher.to_string()
+}
+
+/// kind of a type resolved from typedefs
+#[derive(Debug, Clone, PartialEq)]
+enum TypeKind {
+    /// struct or union
+    Aggregate,
+    /// enum with its enumerators
+    Enum(Vec<String>),
+    /// function pointer
+    Pointer,
+    /// alias of another type; ex) "unsigned char"
+    Alias(String),
+}
+
+/// types by name from typedefs and tagged definitions; ex) "Direction_t", "enum dir" or "struct motor"
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TypeTable {
+    types: HashMap<String, TypeKind>,
+    /// index of the defining typedef by name
+    origins: HashMap<String, usize>,
+}
+
+impl TypeTable {
+    pub(crate) fn new<'a>(typedefs: impl IntoIterator<Item = &'a Typedefs>) -> Self {
+        let re4head =
+            Regex::new(r"^(?:typedef\s+)?(?<kind>struct|union|enum)\b\s*(?<tag>\w+)?\s*\{")
+                .unwrap();
+        let re4fptr = Regex::new(r"^typedef\b.*\(\s*\*\s*(?<name>\w+)\s*\)").unwrap();
+        let re4alias = Regex::new(r"^typedef\s+(?<target>.+?)\s*\b(?<name>\w+)\s*;$").unwrap();
+        let re4name = Regex::new(r"^\w+$").unwrap();
+        let mut types = HashMap::new();
+        let mut origins = HashMap::new();
+        for (index, typedef) in typedefs.into_iter().enumerate() {
+            let mut entries = Vec::new();
+            let captured = typedef.captured.trim();
+            if let Some(cap) = re4head.captures(captured) {
+                let open = cap.get(0).unwrap().end();
+                let close = captured.rfind('}').unwrap_or(captured.len());
+                let kind = match &cap["kind"] {
+                    "enum" => TypeKind::Enum(
+                        captured
+                            .get(open..close)
+                            .unwrap_or_default()
+                            .split(',')
+                            .filter_map(|item| item.split('=').next())
+                            .map(|name| name.trim().to_string())
+                            .filter(|name| re4name.is_match(name))
+                            .collect(),
+                    ),
+                    _ => TypeKind::Aggregate,
+                };
+                if let Some(tag) = cap.name("tag") {
+                    entries.push((format!("{} {}", &cap["kind"], tag.as_str()), kind.clone()));
+                }
+                // names after the closing brace; pointer aliases are pointers
+                let names = captured.get(close + 1..).unwrap_or_default();
+                for name in names.trim_end_matches(';').split(',') {
+                    let name = name.trim();
+                    if let Some(name) = name.strip_prefix('*') {
+                        entries.push((name.trim().to_string(), TypeKind::Pointer));
+                    } else if re4name.is_match(name) {
+                        entries.push((name.to_string(), kind.clone()));
+                    }
+                }
+            } else if let Some(cap) = re4fptr.captures(captured) {
+                entries.push((cap["name"].to_string(), TypeKind::Pointer));
+            } else if let Some(cap) = re4alias.captures(captured) {
+                let target = cap["target"].trim();
+                let kind = if target.ends_with('*') {
+                    TypeKind::Pointer
+                } else {
+                    TypeKind::Alias(strip_qualifiers(target))
+                };
+                entries.push((cap["name"].to_string(), kind));
+            }
+            for (name, kind) in entries {
+                origins.insert(name.clone(), index);
+                types.insert(name, kind);
+            }
+        }
+        Self { types, origins }
+    }
+
+    /// index of the typedef defining the type; aliases are not followed
+    ///
+    pub(crate) fn index_of(&self, dtype: &str) -> Option<usize> {
+        self.origins.get(dtype).copied()
+    }
+
+    /// kind of the type following aliases
+    ///
+    fn resolve(&self, dtype: &str) -> Option<&TypeKind> {
+        let mut dtype = dtype.to_string();
+        // limited depth against cyclic aliases
+        for _ in 0..16 {
+            match self.types.get(&dtype)? {
+                TypeKind::Alias(target) if !self.types.contains_key(target) => {
+                    return self.types.get(&dtype);
+                }
+                TypeKind::Alias(target) => dtype = target.clone(),
+                kind => return Some(kind),
+            }
+        }
+        None
+    }
+}
+
+fn c_default_value(dtype: &str, types: &TypeTable) -> String {
+    if is_pointer(dtype) {
+        return "NULL".to_string();
+    }
+    let mut dtype = strip_qualifiers(dtype);
+    match types.resolve(&dtype) {
+        Some(TypeKind::Pointer) => return "NULL".to_string(),
+        Some(TypeKind::Aggregate) => return "{0}".to_string(),
+        Some(TypeKind::Enum(enumerators)) => {
+            return enumerators.first().map_or("0".to_string(), |e| e.clone());
+        }
+        Some(TypeKind::Alias(target)) => dtype = target.clone(),
+        None => {}
+    }
+    let value = match dtype.as_str() {
+        "float" | "double" | "long double" | "float32" | "float64" => "0.0",
+        "bool" | "_Bool" | "boolean" => "false",
+        t if t.starts_with("struct ") || t.starts_with("union ") => "{0}",
+        _ => "0",
+    };
+    value.to_string()
+}
+
+/// padding of the text to the width; side is "left", "right" or "center"
+///
+fn pad(text: &str, width: usize, side: &str) -> String {
+    let fill = width.saturating_sub(text.chars().count());
+    match side {
+        "right" => format!("{}{}", " ".repeat(fill), text),
+        "center" => format!(
+            "{}{}{}",
+            " ".repeat(fill / 2),
+            text,
+            " ".repeat(fill - fill / 2)
+        ),
+        _ => format!("{}{}", text, " ".repeat(fill)),
+    }
+}
+
+fn side_of(args: &HashMap<String, Value>) -> tera::Result<String> {
+    match args.get("side") {
+        None => Ok("left".to_string()),
+        Some(Value::String(side)) if matches!(side.as_str(), "left" | "right" | "center") => {
+            Ok(side.clone())
+        }
+        Some(side) => Err(tera::Error::msg(format!(
+            "`side` should be \"left\", \"right\" or \"center\", got {}",
+            side
+        ))),
+    }
+}
+
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn align_filter(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let width = args
+        .get("width")
+        .and_then(|w| w.as_u64())
+        .ok_or_else(|| tera::Error::msg("`align` expects a `width` argument"))?;
+    Ok(Value::String(pad(
+        &to_text(value),
+        width as usize,
+        &side_of(args)?,
+    )))
+}
+
+fn column_filter(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| tera::Error::msg(format!("`column` expects a list, got {}", value)))?;
+    let texts: Vec<String> = items.iter().map(to_text).collect();
+    let width = texts.iter().map(|t| t.chars().count()).max().unwrap_or(0);
+    let side = side_of(args)?;
+    Ok(Value::Array(
+        texts
+            .iter()
+            .map(|t| Value::String(pad(t, width, &side)))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_filters() {
+        assert_eq!(snake_case("setDir"), "set_dir");
+        assert_eq!(snake_case("HTTPServer_init2"), "http_server_init2");
+        assert_eq!(upper_snake("getCurrentTime"), "GET_CURRENT_TIME");
+        assert_eq!(camel_case("Motor_set_dir"), "motorSetDir");
+        assert_eq!(pascal_case("motor_set_dir"), "MotorSetDir");
+        assert_eq!(pascal_case("uint8_t"), "Uint8T");
+    }
+
+    #[test]
+    fn test_type_filters() {
+        assert_eq!(
+            strip_qualifiers("const volatile uint8_t * const"),
+            "uint8_t *"
+        );
+        assert_eq!(strip_qualifiers("char*const*"), "char **");
+        assert_eq!(base_type("const struct motor **"), "struct motor");
+        assert!(is_pointer("int []"));
+        assert!(!is_pointer("uint32_t"));
+        assert_eq!(deref_type("const char **"), "const char *");
+        assert_eq!(deref_type("char * const"), "char");
+        assert_eq!(printf_format("const uint32_t"), "\"%\" PRIu32");
+        assert_eq!(printf_format("const char *"), "\"%s\"");
+        assert_eq!(printf_format("int64_t"), "\"%\" PRId64");
+        assert_eq!(printf_format("sint16"), "\"%\" PRId16");
+        assert_eq!(printf_format("uintptr_t"), "\"%\" PRIuPTR");
+        assert_eq!(printf_format("unsigned long"), "\"%lu\"");
+        assert_eq!(printf_format("Direction_t"), "\"%d\"");
+        assert_eq!(gmock_matcher("const char*"), "StrEq");
+        assert_eq!(gmock_matcher("double"), "DoubleEq");
+        assert_eq!(
+            c_escape("say \"hi\"\n\u{e9}"),
+            "say \\\"hi\\\"\\n\\303\\251"
+        );
+    }
+
+    #[test]
+    fn test_c_default_value() {
+        let typedefs: Vec<Typedefs> = [
+            "typedef enum\n{\n    Idle = 0,\n    Forward\n} Direction_t;",
+            "typedef struct { int x; } Point_t, *PPoint_t;",
+            "typedef unsigned char uint8;",
+            "typedef uint8 Level_t;",
+            "typedef void (*Callback_t)(int);",
+            "enum mode { MODE_A, MODE_B };",
+            "typedef float speed_t;",
+        ]
+        .iter()
+        .map(|captured| Typedefs {
+            captured: captured.to_string(),
+            file: String::new(),
+            line: 0,
+        })
+        .collect();
+        let types = TypeTable::new(&typedefs);
+        assert_eq!(c_default_value("Direction_t", &types), "Idle");
+        assert_eq!(c_default_value("enum mode", &types), "MODE_A");
+        assert_eq!(c_default_value("const Point_t", &types), "{0}");
+        assert_eq!(c_default_value("PPoint_t", &types), "NULL");
+        assert_eq!(c_default_value("Callback_t", &types), "NULL");
+        assert_eq!(c_default_value("Level_t", &types), "0");
+        assert_eq!(c_default_value("speed_t", &types), "0.0");
+        assert_eq!(c_default_value("uint8_t *", &types), "NULL");
+        assert_eq!(c_default_value("struct motor", &types), "{0}");
+    }
+
+    #[test]
+    fn test_table_filters() {
+        let mut tera = Tera::default();
+        register_filters(&mut tera, TypeTable::default());
+        let mut context = tera::Context::new();
+        context.insert("rtypes", &["void", "uint8_t", "int"]);
+        let rendered = tera
+            .render_str(
+                "{% for t in rtypes | column %}[{{ t }}]{% endfor %}|{{ 7 | align(width=3, side=\"right\") }}|",
+                &context,
+            )
+            .unwrap();
+        assert_eq!(rendered, "[void   ][uint8_t][int    ]|  7|");
+    }
+}
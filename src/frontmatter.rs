@@ -0,0 +1,138 @@
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+use std::str::FromStr;
+
+/// policy for an output file which already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overwrite {
+    /// keep the manual sections of the existing file
+    #[default]
+    Merge,
+    /// replace the existing file
+    Always,
+    /// leave the existing file as it is
+    Never,
+}
+
+impl FromStr for Overwrite {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "merge" => Ok(Overwrite::Merge),
+            "always" => Ok(Overwrite::Always),
+            "never" => Ok(Overwrite::Never),
+            _ => Err(format!(
+                "unknown overwrite `{}`; use merge, always or never",
+                s
+            )),
+        }
+    }
+}
+
+/// settings in the comment at the top of a jinja2 template;
+/// ex) `{# cyagen: output = "mock_{{ sourcename }}.h", when = "callees | length > 0", overwrite = "never" #}`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrontMatter {
+    /// output filename rendered as a template, relative to the output directory without ".."; ex) "test_{{ fnc.name | snake_case }}.cc"
+    pub output: Option<String>,
+    /// tera expression to generate the output only if true; ex) "fncs | length > 0"
+    pub when: Option<String>,
+    /// merge, always or never
+    pub overwrite: Overwrite,
+}
+
+impl FrontMatter {
+    /// split the front matter from the template; the comment is kept with the line break after it
+    /// moved into the comment, so the line numbers of the template do not change
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let temp = "{# cyagen: when = \"fncs | length > 0\", overwrite = \"never\" #}\nhello";
+    /// let (front, temp) = cyagen::FrontMatter::split(temp).unwrap();
+    /// let front = front.unwrap();
+    /// assert_eq!(front.when.as_deref(), Some("fncs | length > 0"));
+    /// assert_eq!(front.overwrite, cyagen::Overwrite::Never);
+    /// assert!(temp.ends_with("\n#}hello"));
+    /// ```
+    pub fn split(template: &str) -> Result<(Option<Self>, String)> {
+        let re = Regex::new(r"^\s*\{#-?\s*cyagen:(?<body>(?s:.*?))-?#\}").unwrap();
+        let Some(cap) = re.captures(template) else {
+            return Ok((None, template.to_string()));
+        };
+        let front = Self::parse(&cap["body"])?;
+        let end = cap.get(0).unwrap().end();
+        let head = template[..end].trim_end_matches("#}");
+        let rest = &template[end..];
+        let (newline, rest) = match rest.strip_prefix("\r\n") {
+            Some(rest) => ("\r\n", rest),
+            None => match rest.strip_prefix('\n') {
+                Some(rest) => ("\n", rest),
+                None => ("", rest),
+            },
+        };
+        Ok((Some(front), format!("{}{}#}}{}", head, newline, rest)))
+    }
+
+    /// items of `key = "value"` separated by commas or line breaks; single quoted values are taken literally
+    ///
+    fn parse(body: &str) -> Result<Self> {
+        let re = Regex::new(
+            r#"^[\s,]*(?<key>\w+)\s*=\s*(?<value>"(?:[^"\\]|\\.)*"|'[^']*')[ \t]*(?:,|\n|$)"#,
+        )
+        .unwrap();
+        let mut front = FrontMatter::default();
+        let mut rest = body.trim();
+        while !rest.is_empty() {
+            let Some(cap) = re.captures(rest) else {
+                bail!("invalid front matter `{}`", rest);
+            };
+            let value: toml::Value = toml::from_str(&format!("value = {}", &cap["value"]))?;
+            let value = value["value"].as_str().unwrap_or_default().to_string();
+            match &cap["key"] {
+                "output" => front.output = Some(value),
+                "when" => front.when = Some(value),
+                "overwrite" => front.overwrite = value.parse().map_err(|e: String| anyhow!(e))?,
+                key => bail!(
+                    "unknown key `{}` of front matter; use output, when or overwrite",
+                    key
+                ),
+            }
+            rest = rest[cap.get(0).unwrap().end()..].trim();
+        }
+        Ok(front)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter() {
+        let temp = "\
+{# cyagen:
+   output = \"mock_{{ sourcename }}.h\"
+   when = 'callees | filter(attribute=\"name\", value=\"stop\") | length > 0'
+#}
+line 5";
+        let (front, text) = FrontMatter::split(temp).unwrap();
+        let front = front.unwrap();
+        assert_eq!(front.output.as_deref(), Some("mock_{{ sourcename }}.h"));
+        assert_eq!(
+            front.when.as_deref(),
+            Some("callees | filter(attribute=\"name\", value=\"stop\") | length > 0")
+        );
+        assert_eq!(front.overwrite, Overwrite::Merge);
+        assert_eq!(text.lines().count(), 5);
+        assert!(text.ends_with("\n\n#}line 5"));
+
+        let (front, text) = FrontMatter::split("{# a comment #}\nbody").unwrap();
+        assert!(front.is_none());
+        assert_eq!(text, "{# a comment #}\nbody");
+        assert!(FrontMatter::split("{# cyagen: overwrite = \"sometimes\" #}").is_err());
+        assert!(FrontMatter::split("{# cyagen: outptu = \"a\" #}").is_err());
+        assert!(FrontMatter::split("{# cyagen: output #}").is_err());
+    }
+}
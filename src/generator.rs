@@ -1,12 +1,22 @@
 use super::parser::Parser;
+use super::project::Project;
 
-use anyhow::{Context, Result};
+use super::filters::{register_filters, TypeTable};
+use super::frontmatter::{FrontMatter, Overwrite};
+use super::queries::register_functions;
+use super::templates::TemplateSet;
+use anyhow::{anyhow, Context, Result};
 use chrono::Utc;
 use regex::Regex;
 use serde_json;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
 use std::fs;
+use std::path::{Component, Path, PathBuf};
 
 use tera;
+
 use uuid::Uuid;
 
 const NAMESPACE_OID: Uuid = Uuid::from_u128(0x6ba7b812_9dad_11d1_80b4_00c04fd430c8);
@@ -22,25 +32,528 @@ fn generate_uuid(
     Ok(tera::to_value(uuid.hyphenated().to_string()).unwrap())
 }
 
-/// Function to generate UUID
-pub fn generate_using_tera<'a>(parser: &'a Parser, template: &'a str) -> String {
+/// error of rendering a template
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderError {
+    /// template file path; empty if the template is given as text
+    pub template: String,
+    /// line number in the template if known; tera only gives the position of syntax errors,
+    /// so runtime errors such as an undefined variable are reported with the template alone
+    pub line: Option<usize>,
+    /// column number in the template if known
+    pub column: Option<usize>,
+    /// error message including the chain of causes
+    pub message: String,
+}
+
+impl RenderError {
+    /// set the template file path of the error
+    ///
+    pub fn with_template(mut self, template: &str) -> Self {
+        self.template = template.to_string();
+        self
+    }
+
+    /// error of tera; the position is taken from the message of a syntax error such as "--> 3:7",
+    /// and runtime errors have no position
+    ///
+    fn from_tera(error: tera::Error) -> Self {
+        let mut messages = vec![error.to_string()];
+        let mut source = error.source();
+        while let Some(cause) = source {
+            messages.push(cause.to_string());
+            source = cause.source();
+        }
+        let message = messages.join("\n");
+        let re = Regex::new(r"--> (?<line>\d+):(?<column>\d+)").unwrap();
+        let cap = re.captures(&message);
+        Self {
+            template: String::new(),
+            line: cap.as_ref().and_then(|c| c["line"].parse().ok()),
+            column: cap.as_ref().and_then(|c| c["column"].parse().ok()),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let template = if self.template.is_empty() {
+            "<template>"
+        } else {
+            &self.template
+        };
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{}:{}: ", template, line, column)?,
+            (Some(line), None) => write!(f, "{}:{}: ", template, line)?,
+            _ => write!(f, "{}: ", template)?,
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for RenderError {}
+
+/// generate document based on parsing result and jinja2 template
+///
+/// # Available filters in template file
+/// - **generateUUID** : `{{ "motor" | generateUUID }}` -> UUID v5 of the text
+/// - **snake_case** : `{{ "setDir" | snake_case }}` -> `set_dir`
+/// - **camel_case** : `{{ "set_dir" | camel_case }}` -> `setDir`
+/// - **pascal_case** : `{{ "set_dir" | pascal_case }}` -> `SetDir`
+/// - **upper_snake** : `{{ "setDir" | upper_snake }}` -> `SET_DIR`
+/// - **c_default_value** : zero value of the data type; `0`, `0.0`, `false`, `NULL`, `{0}` or the first enumerator
+///     - `{{ "uint8_t *" | c_default_value }}` -> `NULL`
+///     - `{{ "Direction_t" | c_default_value }}` -> `Idle` with `typedef enum { Idle, Forward } Direction_t;`
+/// - **strip_qualifiers** : `{{ "const volatile uint8_t * const" | strip_qualifiers }}` -> `uint8_t *`
+/// - **base_type** : `{{ "const struct motor **" | base_type }}` -> `struct motor`
+/// - **is_pointer** : `{% if arg_type | is_pointer %}` is true for `char *` or `int []`
+/// - **deref_type** : `{{ "const char **" | deref_type }}` -> `const char *`
+/// - **c_escape** : escape the text to be put in a string literal; `"{{ fnc.captured | c_escape }}"`
+/// - **printf_format** : conversion as a string literal; `printf("speed: " {{ "uint32_t" | printf_format }} "\n", speed);`
+///     - `{{ "uint32_t" | printf_format }}` -> `"%" PRIu32` of <inttypes.h> for fixed-width types
+///     - `{{ "unsigned int" | printf_format }}` -> `"%u"`
+/// - **gmock_matcher** : `{{ "const char *" | gmock_matcher }}` -> `StrEq`; `FloatEq`, `DoubleEq` or `Eq` otherwise
+/// - **align** : pad to the width; `{{ fnc.rtype | align(width=10) }}` with `side` of "left" (default), "right" or "center"
+/// - **column** : pad a list of texts to the widest one for neat tables
+///
+/// # Available functions in template file
+/// - **fnc_by_name** : `{% set fnc = fnc_by_name(name="setDir") %}` function of the name or qualified name, or null
+/// - **has_function** : `{% if has_function(name="init") %}` is true if the function is defined in the source
+/// - **callers_of** : `{% for caller in callers_of(name="setDir") %}` functions calling the function
+/// - **callees_of** : `{% for callee in callees_of(name="setDir") %}` functions and external calls made by the function
+/// - **statics_of** : `{% for var in statics_of(name="setDir") %}` local static variables of the function
+/// - **typedef_of** : `{% set typedef = typedef_of(dtype=arg.dtype) %}` typedef of the base type, or null
+///
+/// # Example
+///
+/// ```
+/// let parser = cyagen::Parser::parse("unsigned int getCurrentTime(void)\n{\n    return 0;\n}\n");
+/// let temp = "\
+/// {%- set rtypes = fncs | map(attribute=\"rtype\") | column %}
+/// {%- for fnc in fncs %}
+/// {{ rtypes[loop.index0] }} {{ fnc.name | upper_snake }} = {{ fnc.rtype | c_default_value }}; /* {{ fnc.rtype | printf_format }} */
+/// {%- endfor %}";
+/// let gen = cyagen::generate_using_tera(&parser, temp).unwrap();
+/// assert_eq!(gen, "\nunsigned int GET_CURRENT_TIME = 0; /* \"%u\" */");
+/// ```
+pub fn generate_using_tera<'a>(
+    parser: &'a Parser,
+    template: &'a str,
+) -> Result<String, RenderError> {
+    generate_using_tera_with(parser, template, &tera::Context::new())
+}
+
+/// render the template with the parser and extra data; ex) project name, owner or test vectors
+/// given by `--data` and `--var`; the extra data replaces the identifiers of the same name
+///
+/// # Example
+///
+/// ```
+/// let parser = cyagen::Parser::parse("void setDir(int dir)\n{\n}\n");
+/// let mut extra = tera::Context::new();
+/// extra.insert("owner", "motor-team");
+/// let gen = cyagen::generate_using_tera_with(&parser, "{{ owner }}: {{ fncs.0.name }}", &extra).unwrap();
+/// assert_eq!(gen, "motor-team: setDir");
+/// ```
+pub fn generate_using_tera_with(
+    parser: &Parser,
+    template: &str,
+    extra: &tera::Context,
+) -> Result<String, RenderError> {
+    let json_data = parser_to_value(parser);
+    let mut tera = new_tera(TypeTable::default());
+    bind_parser(&mut tera, parser, &json_data);
+    let mut context = context_of(&json_data);
+    context.extend(extra.clone());
+    render_using_tera(tera, context, template)
+}
+
+/// render the template once with the whole project; `modules`, `symbols`, `links`,
+/// `undefined` and `multiply_defined` are available in the template
+pub fn generate_project_using_tera<'a>(
+    project: &'a Project,
+    template: &'a str,
+) -> Result<String, RenderError> {
+    render_using_tera(
+        new_tera(project_types(project)),
+        context_of(&serde_json::to_value(project).unwrap()),
+        template,
+    )
+}
+
+fn render_using_tera(
+    mut tera: tera::Tera,
+    context: tera::Context,
+    template: &str,
+) -> Result<String, RenderError> {
+    // render template
+    tera.render_str(template, &context)
+        .map_err(RenderError::from_tera)
+}
+
+/// tera instance with the filters of cyagen; types are looked up by `c_default_value`
+///
+fn new_tera(types: TypeTable) -> tera::Tera {
     let mut tera = tera::Tera::default();
 
     // register filter function
     tera.register_filter("generateUUID", generate_uuid);
+    register_filters(&mut tera, types);
+    tera
+}
+
+/// bind the filters looking up the types and the query functions to the parser
+///
+fn bind_parser(tera: &mut tera::Tera, parser: &Parser, json_data: &tera::Value) {
+    let types = TypeTable::new(&parser.typedefs);
+    register_filters(tera, types.clone());
+    register_functions(tera, json_data, types);
+}
+
+/// types of all the modules of the project
+///
+fn project_types(project: &Project) -> TypeTable {
+    TypeTable::new(project.modules.iter().flat_map(|m| &m.typedefs))
+}
 
-    // prepare context from parser
-    let json_data: tera::Value = serde_json::to_value(&parser).unwrap();
+/// prepare context from json data
+///
+fn context_of(json_data: &tera::Value) -> tera::Context {
     let mut context = tera::Context::new();
     for (key, value) in json_data.as_object().unwrap() {
         context.insert(key, value);
     }
+    context
+}
 
-    // render template
-    let result = tera.render_str(&template, &context);
-    match result {
-        Ok(value) => value,
-        Err(error) => panic!("Error: {}", error),
+/// json data of the parser for templates
+///
+fn parser_to_value(parser: &Parser) -> tera::Value {
+    let mut json_data = serde_json::to_value(parser).unwrap();
+    // lists of extractors are available by their names as well; ex) "dem_events"
+    let object = json_data.as_object_mut().unwrap();
+    for (name, list) in &parser.extracted {
+        if !object.contains_key(name) {
+            object.insert(name.clone(), serde_json::to_value(list).unwrap());
+        }
+    }
+    json_data
+}
+
+/// true if the template is jinja2 format such as .tera, .j2 or .njk;
+/// otherwise cyagen old style of template format
+///
+pub fn is_tera_template(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("tera") | Some("j2") | Some("njk")
+    )
+}
+
+/// output of a template rendered with its front matter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rendered {
+    /// generated text
+    pub text: String,
+    /// output filename given by the front matter; relative to the output directory
+    pub output: Option<String>,
+    /// policy for the existing output file
+    pub overwrite: Overwrite,
+}
+
+impl From<String> for Rendered {
+    fn from(text: String) -> Self {
+        Rendered {
+            text,
+            ..Default::default()
+        }
+    }
+}
+
+/// jinja2 templates of a directory loaded into one tera instance; templates can include, extend and
+/// import each other by their relative paths such as "_partials/mocks.njk"
+pub struct TemplateDir {
+    /// template directory
+    pub dir: PathBuf,
+    tera: tera::Tera,
+    /// file paths of the templates by name to report errors
+    paths: BTreeMap<String, PathBuf>,
+    /// front matters of the templates by name
+    front_matters: BTreeMap<String, FrontMatter>,
+    /// errors of the templates failed to load by name
+    errors: BTreeMap<String, RenderError>,
+}
+
+impl TemplateDir {
+    /// load jinja2 templates and partials of the directory and its sub-directories
+    ///
+    pub fn load(dir: &Path) -> Result<Self> {
+        Self::from_set(&TemplateSet::load(dir)?)
+    }
+
+    /// load jinja2 templates and partials of the template set
+    ///
+    pub fn from_set(set: &TemplateSet) -> Result<Self> {
+        let templates = set
+            .files
+            .iter()
+            .filter(|(name, _)| is_tera_template(Path::new(name)) || Self::is_partial(name))
+            .map(|(name, file)| (name.clone(), file.text.clone()))
+            .collect();
+        let mut templates = Self::from_templates(&set.dir, templates)?;
+        for (name, file) in &set.files {
+            if let Some(error) = templates.errors.get_mut(name) {
+                error.template = file.path.to_string_lossy().to_string();
+            }
+            templates.paths.insert(name.clone(), file.path.clone());
+        }
+        Ok(templates)
+    }
+
+    /// load the given templates as (name, text); templates failed to load are left out,
+    /// and their errors are returned when rendered
+    ///
+    pub fn from_templates(dir: &Path, templates: Vec<(String, String)>) -> Result<Self> {
+        let re = Regex::new(r"['`](?<name>[^'`]+)['`]").unwrap();
+        let mut errors = BTreeMap::new();
+        let mut front_matters = BTreeMap::new();
+        let mut texts = vec![];
+        for (name, text) in templates {
+            let path = dir.join(&name).to_string_lossy().to_string();
+            match FrontMatter::split(&text) {
+                Ok((front, text)) => {
+                    if let Some(front) = front {
+                        front_matters.insert(name.clone(), front);
+                    }
+                    texts.push((name, text));
+                }
+                Err(e) => {
+                    let error = RenderError {
+                        template: path,
+                        line: Some(1),
+                        column: None,
+                        message: e.to_string(),
+                    };
+                    errors.insert(name, error);
+                }
+            }
+        }
+        let mut templates = texts;
+        loop {
+            let mut tera = new_tera(TypeTable::default());
+            let Err(error) = tera.add_raw_templates(templates.iter().map(|(n, t)| (n, t))) else {
+                return Ok(Self {
+                    dir: dir.to_path_buf(),
+                    tera,
+                    paths: BTreeMap::new(),
+                    front_matters,
+                    errors,
+                });
+            };
+            // the first quoted name of the message is the template in error
+            let error = RenderError::from_tera(error);
+            let index = re
+                .captures(&error.message)
+                .and_then(|cap| templates.iter().position(|(n, _)| *n == cap["name"]));
+            let Some(index) = index else {
+                return Err(anyhow!(error.with_template(&dir.to_string_lossy())));
+            };
+            let (name, _) = templates.remove(index);
+            let path = dir.join(&name).to_string_lossy().to_string();
+            errors.insert(name, error.with_template(&path));
+        }
+    }
+
+    /// name of the template file; relative path from the directory with "/"
+    ///
+    pub fn name_of(&self, path: &Path) -> String {
+        let path = path.strip_prefix(&self.dir).unwrap_or(path);
+        path.iter()
+            .map(|c| c.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// true if the template is only loaded to be included, extended or imported;
+    /// files in a directory prefixed with an underscore such as "_partials/" or prefixed with an underscore
+    ///
+    pub fn is_partial(name: &str) -> bool {
+        name.split('/').any(|c| c.starts_with('_'))
+    }
+
+    /// errors of the templates failed to load by name
+    ///
+    pub fn errors(&self) -> &BTreeMap<String, RenderError> {
+        &self.errors
+    }
+
+    /// file path of the template to report errors
+    ///
+    pub fn path_of(&self, name: &str) -> PathBuf {
+        self.paths
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| self.dir.join(name))
+    }
+
+    /// front matter of the template if given
+    ///
+    pub fn front_matter(&self, name: &str) -> Option<&FrontMatter> {
+        self.front_matters.get(name)
+    }
+
+    /// render the template of the name with the parser and extra data;
+    /// `None` if the condition of the front matter is false
+    ///
+    pub fn render(
+        &self,
+        name: &str,
+        parser: &Parser,
+        extra: &tera::Context,
+    ) -> Result<Option<Rendered>, RenderError> {
+        self.bind(parser).render(name, extra)
+    }
+
+    /// render the template of the name with the whole project and extra data;
+    /// `None` if the condition of the front matter is false
+    ///
+    pub fn render_project(
+        &self,
+        name: &str,
+        project: &Project,
+        extra: &tera::Context,
+    ) -> Result<Option<Rendered>, RenderError> {
+        self.bind_project(project).render(name, extra)
+    }
+
+    /// templates bound to the parser to render several templates of the same source
+    ///
+    pub fn bind(&self, parser: &Parser) -> BoundTemplates<'_> {
+        let json_data = parser_to_value(parser);
+        let mut tera = self.tera.clone();
+        bind_parser(&mut tera, parser, &json_data);
+        BoundTemplates {
+            templates: self,
+            tera,
+            context: context_of(&json_data),
+        }
+    }
+
+    /// templates bound to the whole project
+    ///
+    pub fn bind_project(&self, project: &Project) -> BoundTemplates<'_> {
+        let mut tera = self.tera.clone();
+        register_filters(&mut tera, project_types(project));
+        BoundTemplates {
+            templates: self,
+            tera,
+            context: context_of(&serde_json::to_value(project).unwrap()),
+        }
+    }
+}
+
+/// templates of a directory bound to a model; filters, functions and the context of the model
+/// are prepared once and shared by the templates rendered with it
+pub struct BoundTemplates<'a> {
+    templates: &'a TemplateDir,
+    tera: tera::Tera,
+    context: tera::Context,
+}
+
+impl BoundTemplates<'_> {
+    /// render the template of the name with extra data replacing the identifiers of the same name;
+    /// `None` if the condition of the front matter is false
+    ///
+    pub fn render(
+        &mut self,
+        name: &str,
+        extra: &tera::Context,
+    ) -> Result<Option<Rendered>, RenderError> {
+        // extra data is put into the context of the model during the rendering,
+        // and the replaced identifiers are restored afterwards
+        let extra = match extra.clone().into_json() {
+            tera::Value::Object(extra) => extra,
+            _ => Default::default(),
+        };
+        let replaced: Vec<(String, Option<tera::Value>)> = extra
+            .into_iter()
+            .map(|(key, value)| {
+                let old = self.context.remove(&key);
+                self.context.insert(key.clone(), &value);
+                (key, old)
+            })
+            .collect();
+        let result = self.render_with_context(name);
+        for (key, old) in replaced {
+            self.context.remove(&key);
+            if let Some(old) = old {
+                self.context.insert(key, &old);
+            }
+        }
+        result
+    }
+
+    fn render_with_context(&mut self, name: &str) -> Result<Option<Rendered>, RenderError> {
+        if let Some(error) = self.templates.errors.get(name) {
+            return Err(error.clone());
+        }
+        let (tera, context) = (&mut self.tera, &self.context);
+        let path = self.templates.path_of(name).to_string_lossy().to_string();
+        let front = self
+            .templates
+            .front_matters
+            .get(name)
+            .cloned()
+            .unwrap_or_default();
+        // items of the front matter are evaluated in the same context
+        let mut render_item = |key: &str, template: &str| {
+            tera.render_str(template, context).map_err(|e| {
+                let mut error = RenderError::from_tera(e).with_template(&path);
+                error.message = format!("invalid `{}` of front matter: {}", key, error.message);
+                error.line = Some(1);
+                error
+            })
+        };
+        if let Some(when) = &front.when {
+            let template = format!("{{% if {} %}}true{{% endif %}}", when);
+            if render_item("when", &template)? != "true" {
+                return Ok(None);
+            }
+        }
+        let output = match &front.output {
+            Some(output) => {
+                let output = render_item("output", output)?.trim().to_string();
+                // outputs are kept within the output directory
+                let is_outside = Path::new(&output).components().any(|c| {
+                    matches!(
+                        c,
+                        Component::Prefix(_) | Component::RootDir | Component::ParentDir
+                    )
+                });
+                if is_outside {
+                    return Err(RenderError {
+                        template: path,
+                        line: Some(1),
+                        column: None,
+                        message: format!(
+                            "invalid `output` of front matter: `{}` should be relative to the output directory without `..`",
+                            output
+                        ),
+                    });
+                }
+                Some(output)
+            }
+            None => None,
+        };
+        let text = tera
+            .render(name, context)
+            .map_err(|e| RenderError::from_tera(e).with_template(&path))?;
+        Ok(Some(Rendered {
+            text,
+            output,
+            overwrite: front.overwrite,
+        }))
     }
 }
 
@@ -134,9 +647,43 @@ pub fn merge_with_manual_sections(rendered: &str, old_gen: &str) -> String {
 /// @end-fncs@
 /// ";
 /// let parser = cyagen::Parser::parse(code);
-/// let gen = cyagen::generate(&parser, temp, sourcename);
+/// let gen = cyagen::generate(&parser, temp, sourcename).unwrap();
 /// ```
-pub fn generate<'a>(parser: &'a Parser, template: &'a str, sourcename: &'a str) -> String {
+pub fn generate<'a>(
+    parser: &'a Parser,
+    template: &'a str,
+    sourcename: &'a str,
+) -> Result<String, RenderError> {
+    // every block tag needs its end tag
+    let tags = [
+        "incs",
+        "static-vars",
+        "static-global-vars",
+        "static-local-vars",
+        "fncs",
+        "fncs0",
+        "ncls",
+        "ncls-once",
+    ];
+    for tag in tags {
+        let begin = format!("@{}@", tag);
+        if let Some(pos) = template.find(&begin) {
+            let end = format!("@end-{}@", tag);
+            if !template.get(pos..).unwrap().contains(&end) {
+                let line_start = template
+                    .get(..pos)
+                    .unwrap()
+                    .rfind('\n')
+                    .map_or(0, |i| i + 1);
+                return Err(RenderError {
+                    template: String::new(),
+                    line: Some(template.get(..pos).unwrap().matches('\n').count() + 1),
+                    column: Some(template.get(line_start..pos).unwrap().chars().count() + 1),
+                    message: format!("`{}` without `{}`", begin, end),
+                });
+            }
+        }
+    }
     let mut output = String::from(template);
     if template.contains("@incs@") {
         let re = Regex::new(r"@incs@(?P<fmt>[\S\s]*)@end-incs@").unwrap();
@@ -324,16 +871,143 @@ pub fn generate<'a>(parser: &'a Parser, template: &'a str, sourcename: &'a str)
             output = re.replace(&output, tmpstr.as_str()).into_owned();
         }
     }
-    output.replace("@sourcename@", sourcename).replace(
+    Ok(output.replace("@sourcename@", sourcename).replace(
         "@date@",
         Utc::now().format("%a %b %e %T %Y").to_string().as_str(),
-    )
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_generate_project_using_tera() {
+        let mut timer = Parser::parse("unsigned int getTime(void)\n{\n    return 0;\n}\n");
+        timer.sourcename = "timer".to_string();
+        let mut motor = Parser::parse("void move(void)\n{\n    getTime();\n}\n");
+        motor.sourcename = "motor".to_string();
+        let project = Project::new(vec![timer, motor]);
+        let temp = "\
+{%- for module in modules %}
+add_subdirectory(test_{{ module.sourcename }})
+{%- endfor %}
+{%- for link in links %}
+{{ link.module }}.{{ link.caller }} -> {{ link.defined_in | join(sep=\",\") }}.{{ link.name }}
+{%- endfor %}
+";
+        let expected = "
+add_subdirectory(test_timer)
+add_subdirectory(test_motor)
+motor.move -> timer.getTime
+";
+        assert_eq!(
+            generate_project_using_tera(&project, temp).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_render_error() {
+        let parser = Parser::parse("int main(void)\n{\n    return 0;\n}\n");
+        let error = generate_using_tera(
+            &parser,
+            "{% for fnc in fncs %}\n{{ fnc.name }\n{% endfor %}",
+        )
+        .unwrap_err()
+        .with_template("templates/test.c.tera");
+        assert_eq!(error.template, "templates/test.c.tera");
+        assert_eq!(error.line, Some(2));
+        assert!(error.to_string().starts_with("templates/test.c.tera:2:"));
+        let error = generate_using_tera(&parser, "{{ unknown.name }}")
+            .unwrap_err()
+            .with_template("templates/test.c.tera");
+        assert!(error.message.contains("unknown"));
+        assert_eq!(error.line, None);
+        assert!(error.to_string().starts_with("templates/test.c.tera: "));
+        let error = generate(&parser, "// functions\n  @fncs@@name@\n", "test").unwrap_err();
+        assert_eq!((error.line, error.column), (Some(2), Some(3)));
+        assert_eq!(error.message, "`@fncs@` without `@end-fncs@`");
+    }
+
+    #[test]
+    fn test_template_dir() {
+        let templates = vec![
+            (
+                "_partials/macros.njk".to_string(),
+                "{% macro mock(fnc) %}MOCK({{ fnc.name }}){% endmacro mock %}".to_string(),
+            ),
+            (
+                "_base.njk".to_string(),
+                "// generated\n{% block body %}{% endblock body %}".to_string(),
+            ),
+            (
+                "test.c.njk".to_string(),
+                "{% extends \"_base.njk\" %}{% import \"_partials/macros.njk\" as m %}\
+{% block body %}{% for fnc in fncs %}{{ m::mock(fnc=fnc) }}{% endfor %}{% endblock body %}"
+                    .to_string(),
+            ),
+            ("broken.njk".to_string(), "\n{{ fncs }".to_string()),
+            (
+                "main.njk".to_string(),
+                "{# cyagen: output = \"{{ fncs.0.name }}_main.c\", overwrite = \"never\" #}\nint x;"
+                    .to_string(),
+            ),
+            (
+                "mock.h.njk".to_string(),
+                "{# cyagen: when = \"callees | length > 0\" #}\nMOCK".to_string(),
+            ),
+            (
+                "bad_front.njk".to_string(),
+                "{# cyagen: overwrite = \"sometimes\" #}".to_string(),
+            ),
+            (
+                "escape.njk".to_string(),
+                "{# cyagen: output = \"../{{ fncs.0.name }}.c\" #}".to_string(),
+            ),
+        ];
+        let dir = TemplateDir::from_templates(Path::new("templates"), templates).unwrap();
+        let parser = Parser::parse("void run(void)\n{\n}\n");
+        assert_eq!(
+            dir.render("test.c.njk", &parser, &tera::Context::new())
+                .unwrap()
+                .unwrap()
+                .text,
+            "// generated\nMOCK(run)"
+        );
+        let rendered = dir
+            .render("main.njk", &parser, &tera::Context::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(rendered.text, "int x;");
+        assert_eq!(rendered.output.as_deref(), Some("run_main.c"));
+        assert_eq!(rendered.overwrite, Overwrite::Never);
+        let rendered = dir.render("mock.h.njk", &parser, &tera::Context::new());
+        assert_eq!(rendered, Ok(None));
+        assert!(dir.errors().contains_key("bad_front.njk"));
+        let error = dir
+            .render("broken.njk", &parser, &tera::Context::new())
+            .unwrap_err();
+        assert_eq!(error.line, Some(2));
+        assert!(error.template.ends_with("broken.njk"));
+        let error = dir
+            .render("escape.njk", &parser, &tera::Context::new())
+            .unwrap_err();
+        assert_eq!(error.line, Some(1));
+        assert!(error.message.contains("`../run.c`"));
+        // extra data replaces the model only while rendering with it
+        let mut bound = dir.bind(&parser);
+        let mut extra = tera::Context::new();
+        extra.insert("fncs", &[serde_json::json!({"name": "stop"})]);
+        let rendered = bound.render("test.c.njk", &extra).unwrap().unwrap();
+        assert_eq!(rendered.text, "// generated\nMOCK(stop)");
+        let rendered = bound.render("test.c.njk", &tera::Context::new());
+        assert_eq!(rendered.unwrap().unwrap().text, "// generated\nMOCK(run)");
+        assert!(TemplateDir::is_partial("_partials/macros.njk"));
+        assert!(TemplateDir::is_partial("_base.njk"));
+        assert!(!TemplateDir::is_partial("test.c.njk"));
+    }
+
     #[test]
     fn test_generate_incs() {
         let sourcename = "test";
@@ -353,7 +1027,7 @@ mod tests {
 
 ";
         let parser = Parser::parse(code);
-        let generated = generate(&parser, temp, sourcename);
+        let generated = generate(&parser, temp, sourcename).unwrap();
         assert_eq!(generated, expected);
     }
 
@@ -395,7 +1069,7 @@ int local_var;
 
 ";
         let parser = Parser::parse(code);
-        let generated = generate(&parser, temp, sourcename);
+        let generated = generate(&parser, temp, sourcename).unwrap();
         assert_eq!(generated, expected);
     }
 
@@ -427,7 +1101,7 @@ const int *
 
 ";
         let parser = Parser::parse(code);
-        let generated = generate(&parser, temp, sourcename);
+        let generated = generate(&parser, temp, sourcename).unwrap();
         assert_eq!(generated, expected);
     }
 
@@ -458,7 +1132,7 @@ int func2(int a)
 
 ";
         let parser = Parser::parse(code);
-        let generated = generate(&parser, temp, sourcename);
+        let generated = generate(&parser, temp, sourcename).unwrap();
         assert_eq!(generated, expected);
     }
 
@@ -489,7 +1163,7 @@ void func3(int a)
 
 ";
         let parser = Parser::parse(code);
-        let generated = generate(&parser, temp, sourcename);
+        let generated = generate(&parser, temp, sourcename).unwrap();
         assert_eq!(generated, expected);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,307 @@
+use super::expr::ConstTable;
+use serde::{Deserialize, Serialize};
+
+/// structured initializer of a variable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Initializer {
+    /// scalar expression; ex) "0x10U" or "Forward"
+    Scalar {
+        /// expression text
+        text: String,
+        /// evaluated value if it is a constant expression
+        value: Option<i64>,
+    },
+    /// string literal; ex) "\"hello\""
+    String {
+        /// literal text including quotes
+        text: String,
+        /// contents without quotes; adjacent literals are concatenated
+        value: String,
+    },
+    /// brace enclosed list; ex) "{ 1, 2, 3 }"
+    List {
+        /// list items in order
+        items: Vec<InitItem>,
+    },
+}
+
+/// item of a brace enclosed initializer list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InitItem {
+    /// designator as written; ex) ".gain", "[2]" or ".coef[1]"
+    pub designator: Option<String>,
+    /// field name of the designator; ex) "gain" for ".gain = 3"
+    pub field: Option<String>,
+    /// index expression of the designator; ex) "2" for "[2] = 3"
+    pub index: Option<String>,
+    /// initializer value
+    pub value: Initializer,
+}
+
+impl Initializer {
+    /// parse initializer text such as "{ .a = 1, .b = { 2, 3 } }"
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let consts = cyagen::ConstTable::default();
+    /// let init = cyagen::Initializer::parse("{ [1] = 10, [0] = 5 }", &consts);
+    /// if let cyagen::Initializer::List { items } = init {
+    ///     assert_eq!(items[0].index.as_deref(), Some("1"));
+    /// }
+    /// ```
+    pub fn parse(text: &str, consts: &ConstTable) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let mut reader = Reader {
+            chars: &chars,
+            pos: 0,
+            consts,
+        };
+        reader.value()
+    }
+}
+
+struct Reader<'a> {
+    chars: &'a [char],
+    pos: usize,
+    consts: &'a ConstTable,
+}
+
+impl Reader<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Initializer {
+        self.skip_spaces();
+        match self.peek() {
+            Some('{') => self.list(),
+            _ => {
+                let text = self.scalar_text();
+                match string_value(&text) {
+                    Some(value) => Initializer::String { text, value },
+                    None => Initializer::Scalar {
+                        value: self.consts.eval(&text),
+                        text,
+                    },
+                }
+            }
+        }
+    }
+
+    fn list(&mut self) -> Initializer {
+        let mut items = vec![];
+        self.pos += 1;
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                None => break,
+                Some('}') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(',') => {
+                    self.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            let designator = self.designator();
+            let (field, index) = match &designator {
+                Some(d) => split_designator(d),
+                None => (None, None),
+            };
+            items.push(InitItem {
+                designator,
+                field,
+                index,
+                value: self.value(),
+            });
+        }
+        Initializer::List { items }
+    }
+
+    /// consume a designator followed by '=' if any
+    ///
+    fn designator(&mut self) -> Option<String> {
+        let start = self.pos;
+        let is_designator = match self.peek() {
+            Some('[') => true,
+            Some('.') => self
+                .chars
+                .get(self.pos + 1)
+                .is_some_and(|c| c.is_alphabetic() || *c == '_'),
+            _ => false,
+        };
+        if !is_designator {
+            return None;
+        }
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                '=' if depth == 0 => {
+                    let designator: String = self.chars[start..self.pos].iter().collect();
+                    self.pos += 1;
+                    return Some(designator.trim().to_string());
+                }
+                ',' | '}' | '{' if depth == 0 => break,
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        self.pos = start;
+        None
+    }
+
+    /// consume a scalar expression up to the next ',' or '}' outside of brackets and quotes
+    ///
+    fn scalar_text(&mut self) -> String {
+        let start = self.pos;
+        let mut depth = 0;
+        let mut quote = None;
+        while let Some(c) = self.peek() {
+            if let Some(q) = quote {
+                if c == '\\' {
+                    self.pos += 1;
+                } else if c == q {
+                    quote = None;
+                }
+            } else {
+                match c {
+                    '"' | '\'' => quote = Some(c),
+                    '(' | '[' => depth += 1,
+                    ')' | ']' => depth -= 1,
+                    ',' | '}' if depth == 0 => break,
+                    _ => {}
+                }
+            }
+            self.pos += 1;
+        }
+        let end = self.pos.min(self.chars.len());
+        let text: String = self.chars[start..end].iter().collect();
+        text.trim().to_string()
+    }
+}
+
+/// field name and index expression of the first element of a designator
+///
+fn split_designator(designator: &str) -> (Option<String>, Option<String>) {
+    if let Some(rest) = designator.strip_prefix('.') {
+        let field: String = rest
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+        (Some(field), None)
+    } else if let Some(rest) = designator.strip_prefix('[') {
+        let mut depth = 1;
+        let index: String = rest
+            .chars()
+            .take_while(|c| {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {}
+                }
+                depth > 0
+            })
+            .collect();
+        (None, Some(index.trim().to_string()))
+    } else {
+        (None, None)
+    }
+}
+
+/// contents of string literals if the text only consists of them; ex) "\"ab\" \"c\"" -> "abc"
+///
+fn string_value(text: &str) -> Option<String> {
+    let mut value = String::new();
+    let mut rest = text.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        // skip encoding prefix such as L, u, U, or u8
+        let literal = rest.trim_start_matches(['L', 'u', 'U', '8']);
+        let literal = literal.strip_prefix('"')?;
+        let mut escaped = false;
+        let end = literal.char_indices().find(|(_, c)| {
+            let is_end = !escaped && *c == '"';
+            escaped = !escaped && *c == '\\';
+            is_end
+        })?;
+        value.push_str(&literal[..end.0]);
+        rest = literal[end.0 + 1..].trim_start();
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar(text: &str, value: Option<i64>) -> Initializer {
+        Initializer::Scalar {
+            text: text.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_parse_scalar_and_string() {
+        let mut consts = ConstTable::default();
+        consts.collect("#define GAIN 3");
+        assert_eq!(
+            Initializer::parse("GAIN * 2U", &consts),
+            scalar("GAIN * 2U", Some(6))
+        );
+        assert_eq!(
+            Initializer::parse(r#""ab\"c" "de""#, &consts),
+            Initializer::String {
+                text: r#""ab\"c" "de""#.to_string(),
+                value: r#"ab\"cde"#.to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_list() {
+        let consts = ConstTable::default();
+        let init = Initializer::parse("{\n    { 1, 2 },\n    { 3, f(4, 5) },\n}", &consts);
+        let Initializer::List { items } = init else {
+            panic!("list expected");
+        };
+        assert_eq!(items.len(), 2);
+        let Initializer::List { items: inner } = &items[1].value else {
+            panic!("list expected");
+        };
+        assert_eq!(inner[0].value, scalar("3", Some(3)));
+        assert_eq!(inner[1].value, scalar("f(4, 5)", None));
+    }
+
+    #[test]
+    fn test_parse_designated() {
+        let consts = ConstTable::default();
+        let init = Initializer::parse(
+            "{ .name = \"left\", .coef[1] = 2, [3] = { .x = 1 }, 4 }",
+            &consts,
+        );
+        let Initializer::List { items } = init else {
+            panic!("list expected");
+        };
+        assert_eq!(items[0].field.as_deref(), Some("name"));
+        assert_eq!(items[1].designator.as_deref(), Some(".coef[1]"));
+        assert_eq!(items[1].field.as_deref(), Some("coef"));
+        assert_eq!(items[2].index.as_deref(), Some("3"));
+        assert_eq!(items[3].designator, None);
+        assert_eq!(items[3].value, scalar("4", Some(4)));
+    }
+}
@@ -1,5 +1,32 @@
+mod compiledb;
+mod config;
+mod data;
+mod encoding;
+mod expr;
+mod extractor;
+mod fanout;
+mod filter;
+mod filters;
+mod frontmatter;
 mod generator;
+mod initializer;
+mod linemap;
 mod parser;
+mod project;
+mod queries;
+mod templates;
 
+pub use compiledb::*;
+pub use config::*;
+pub use data::*;
+pub use encoding::*;
+pub use expr::*;
+pub use extractor::*;
+pub use fanout::*;
+pub use filter::*;
+pub use frontmatter::*;
 pub use generator::*;
+pub use initializer::*;
 pub use parser::*;
+pub use project::*;
+pub use templates::*;
@@ -0,0 +1,167 @@
+use regex::Regex;
+
+/// line number lookup for increasing byte offsets within a text
+pub(crate) struct LineCounter<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> LineCounter<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    /// 1-based line number of the given byte offset
+    ///
+    pub(crate) fn line_at(&mut self, offset: usize) -> usize {
+        if offset < self.pos {
+            self.pos = 0;
+            self.line = 1;
+        }
+        self.line += self.text[self.pos..offset].matches('\n').count();
+        self.pos = offset;
+        self.line
+    }
+}
+
+/// mapping from byte offsets of a text where some parts are replaced to the offsets of the original text
+#[derive(Debug, Default)]
+pub(crate) struct OffsetMap {
+    /// (offset, original offset) where each replaced part ends
+    segments: Vec<(usize, usize)>,
+}
+
+impl OffsetMap {
+    /// record that the given offset of the text corresponds to the original offset
+    ///
+    pub(crate) fn push(&mut self, offset: usize, original: usize) {
+        self.segments.push((offset, original));
+    }
+
+    /// offset of the original text corresponding to the given offset
+    ///
+    pub(crate) fn original(&self, offset: usize) -> usize {
+        match self.segments.partition_point(|(o, _)| *o <= offset) {
+            0 => offset,
+            i => {
+                let (o, original) = self.segments[i - 1];
+                original + offset - o
+            }
+        }
+    }
+}
+
+/// mapping from lines of preprocessed output to their original file and line
+#[derive(Debug, Default)]
+pub(crate) struct LineMap {
+    /// origin of each line; index 0 is line 1
+    origins: Vec<(usize, usize)>,
+    /// file names referred by origins
+    files: Vec<String>,
+}
+
+impl LineMap {
+    /// build line mapping from line markers such as `# 12 "motor.h" 1` or `#line 12 "motor.h"`,
+    /// and return the text where the markers are replaced with empty lines
+    ///
+    pub(crate) fn from_preprocessed(text: &str) -> (Self, String) {
+        let re = Regex::new(
+            r#"^[ \t]*#[ \t]*(?:line[ \t]+)?(?<line>\d+)(?:[ \t]+"(?<file>(?:[^"\\]|\\.)*)")?"#,
+        )
+        .unwrap();
+        let mut map = LineMap::default();
+        let mut output = String::with_capacity(text.len());
+        let mut file = 0;
+        let mut line = 1;
+        for text_line in text.split_inclusive('\n') {
+            if let Some(cap) = re.captures(text_line) {
+                if let Some(name) = cap.name("file") {
+                    let name = name.as_str().replace("\\\\", "\\");
+                    file = match map.files.iter().position(|f| *f == name) {
+                        Some(index) => index,
+                        None => {
+                            map.files.push(name);
+                            map.files.len() - 1
+                        }
+                    };
+                }
+                line = cap.name("line").unwrap().as_str().parse().unwrap_or(1);
+                map.origins.push((file, 0));
+                if text_line.ends_with('\n') {
+                    output.push('\n');
+                }
+            } else {
+                map.origins.push((file, line));
+                line += 1;
+                output.push_str(text_line);
+            }
+        }
+        (map, output)
+    }
+
+    /// the first file named in line markers, which is the preprocessed source itself
+    ///
+    pub(crate) fn primary_file(&self) -> &str {
+        self.files.first().map_or("", |f| f.as_str())
+    }
+
+    /// original file and line of the given line of preprocessed output
+    ///
+    pub(crate) fn origin(&self, line: usize) -> (String, usize) {
+        match self.origins.get(line.wrapping_sub(1)) {
+            Some((file, orig_line)) => (
+                self.files.get(*file).cloned().unwrap_or_default(),
+                *orig_line,
+            ),
+            None => (String::new(), line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_counter() {
+        let text = "a\nb\n\nc";
+        let mut counter = LineCounter::new(text);
+        assert_eq!(counter.line_at(0), 1);
+        assert_eq!(counter.line_at(2), 2);
+        assert_eq!(counter.line_at(5), 4);
+        assert_eq!(counter.line_at(1), 1);
+    }
+
+    #[test]
+    fn test_offset_map() {
+        // "a/*x*/b" -> "ab"
+        let mut map = OffsetMap::default();
+        map.push(1, 6);
+        assert_eq!(map.original(0), 0);
+        assert_eq!(map.original(1), 6);
+    }
+
+    #[test]
+    fn test_line_map() {
+        let text = "\
+# 1 \"motor.c\"
+# 1 \"<built-in>\"
+# 1 \"motor.h\" 1
+int getSpeed(void);
+# 3 \"motor.c\" 2
+
+int speed;
+";
+        let (map, output) = LineMap::from_preprocessed(text);
+        assert_eq!(map.primary_file(), "motor.c");
+        assert_eq!(output.lines().count(), text.lines().count());
+        assert!(!output.contains('#'));
+        assert_eq!(map.origin(4), ("motor.h".to_string(), 1));
+        assert_eq!(map.origin(7), ("motor.c".to_string(), 4));
+    }
+}
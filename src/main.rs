@@ -1,140 +1,685 @@
-use anyhow::{Context, Result};
-use clap::Parser;
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
 use cyagen;
+use std::collections::HashSet;
 use std::fs;
-use std::path::{self, Component, Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 /// text file generator based on C file and templates
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// source file path
+    /// source file path; can be repeated
     #[arg(short, long)]
-    source: String,
-    /// template directory
+    source: Vec<String>,
+    /// template directory; its files override the ones of the template set
     #[arg(short, long)]
     temp_dir: Option<String>,
+    /// built-in template set; ex) "gtest"
+    #[arg(long)]
+    template_set: Option<String>,
     /// output directory
     #[clap(short, long)]
     output_dir: Option<String>,
     /// output json file path
     #[clap[short, long]]
     json_filepath: Option<String>,
+    /// include directory to search headers for constants; can be repeated
+    #[arg(short = 'I', long = "include-dir")]
+    include_dirs: Vec<String>,
+    /// kind of the source file; source or header [default: detected by file extension]
+    #[arg(long)]
+    kind: Option<cyagen::SourceKind>,
+    /// language of the source file; c or cpp [default: detected by file extension]
+    #[arg(long)]
+    language: Option<cyagen::Language>,
+    /// source file is preprocessor output with line markers such as `gcc -E` [default: true for .i and .ii]
+    #[arg(long, overrides_with = "no_preprocessed")]
+    preprocessed: bool,
+    /// source file is not preprocessor output even for .i and .ii
+    #[arg(long, overrides_with = "preprocessed")]
+    no_preprocessed: bool,
+    /// keep elements of preprocessed source originating from included files
+    #[arg(long, overrides_with = "no_all_origins")]
+    all_origins: bool,
+    /// drop elements of preprocessed source originating from included files
+    #[arg(long, overrides_with = "all_origins")]
+    no_all_origins: bool,
+    /// target data model for `sizeof` in constant expressions; ilp32, lp64 or llp64 [default: ilp32]
+    #[arg(long)]
+    data_model: Option<cyagen::DataModel>,
+    /// compile_commands.json to apply -D, -U, -I and -include flags of each source;
+    /// all the translation units are used when no source is given
+    #[arg(long)]
+    compile_db: Option<String>,
+    /// glob pattern to select translation units of compile_commands.json; ex) "src/**/*.c"
+    #[arg(long)]
+    db_filter: Option<String>,
+    /// template directory rendered once with all the sources as a project
+    #[arg(long)]
+    project_temp_dir: Option<String>,
+    /// output directory for project templates [default: output directory]
+    #[arg(long)]
+    project_output_dir: Option<String>,
+    /// character encoding of the sources; auto, utf-8, latin1, windows-1252, shift_jis, ...
+    /// [default: auto; detected by BOM, otherwise UTF-8]
+    #[arg(long)]
+    encoding: Option<cyagen::TextEncoding>,
+    /// functions to keep; glob such as "Motor_*" or regex between slashes such as "/^Motor_/"; can be repeated
+    #[arg(long, value_name = "PATTERN")]
+    include_fn: Vec<cyagen::NamePattern>,
+    /// functions to remove; glob or regex between slashes; can be repeated
+    #[arg(long, value_name = "PATTERN")]
+    exclude_fn: Vec<cyagen::NamePattern>,
+    /// variables to keep; glob or regex between slashes; can be repeated
+    #[arg(long, value_name = "PATTERN")]
+    include_var: Vec<cyagen::NamePattern>,
+    /// variables to remove; glob or regex between slashes; can be repeated
+    #[arg(long, value_name = "PATTERN")]
+    exclude_var: Vec<cyagen::NamePattern>,
+    /// macro defining a local static variable with its argument layout of func, type, name, init or _;
+    /// ex) "TESTABLE_STATIC(type, name, init)"; can be repeated [default: LOCAL_STATIC_VARIABLE(func, type, name, init)]
+    #[arg(long, value_name = "SIGNATURE")]
+    lsv_macro: Vec<cyagen::LsvMacro>,
+    /// config file in TOML with profiles and custom regex extractors as "[[extractors]]"
+    /// [default: cyagen.toml in the working directory or its parents]
+    #[arg(long)]
+    config: Option<String>,
+    /// profile of the config file to run; ex) "gtest" [default: default_profile of the config or "default"]
+    #[arg(long)]
+    profile: Option<String>,
+    /// keep rendering the remaining templates after a template fails
+    #[arg(long, overrides_with = "no_keep_going")]
+    keep_going: bool,
+    /// stop rendering after a template fails
+    #[arg(long, overrides_with = "keep_going")]
+    no_keep_going: bool,
+    /// warn about functions which may reach the end without return
+    #[arg(long)]
+    warn_fall_through: bool,
+    /// data file in json, yaml or toml given to templates under the key; ex) "vectors=./test_vectors.yaml"; can be repeated
+    #[arg(long, value_name = "KEY=PATH")]
+    data: Vec<cyagen::DataFile>,
+    /// variable given to templates; ex) "owner=motor-team"; can be repeated
+    #[arg(long, value_name = "KEY=VALUE")]
+    var: Vec<cyagen::DataVar>,
+    /// macros defined by the config file
+    #[arg(skip)]
+    defines: Vec<(String, String)>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// value of a flag and its "--no-" counterpart; `None` if neither is given
+///
+fn flag(yes: bool, no: bool) -> Option<bool> {
+    match (yes, no) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// take the value of the profile for a flag given neither on the command line nor with its "--no-" counterpart
+///
+fn merge_flag(yes: &mut bool, no: &mut bool, profile: Option<bool>) {
+    if let (None, Some(value)) = (flag(*yes, *no), profile) {
+        *yes = value;
+        *no = !value;
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// manage the built-in template sets
+    Templates {
+        #[command(subcommand)]
+        action: TemplatesAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum TemplatesAction {
+    /// list the built-in template sets
+    List,
+    /// copy a built-in template set into a directory to customize it
+    Export {
+        /// name of the template set; ex) "gtest"
+        name: String,
+        /// directory to write the templates
+        dir: String,
+    },
+}
+
+impl Args {
+    /// fill the options not given on the command line with the profile; variables and data files
+    /// of the command line are given after the ones of the profile to override them
+    ///
+    fn apply(&mut self, profile: cyagen::Profile) -> Result<()> {
+        let text = |path: PathBuf| path.to_string_lossy().to_string();
+        self.defines = profile.define_pairs();
+        if self.source.is_empty() {
+            for pattern in &profile.sources {
+                let mut paths = glob::glob(pattern)
+                    .with_context(|| format!("invalid glob pattern `{}`", pattern))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                if paths.is_empty() {
+                    eprintln!("warning: no source matches `{}`", pattern);
+                }
+                paths.sort();
+                self.source.extend(paths.into_iter().map(text));
+            }
+        }
+        self.temp_dir = self.temp_dir.take().or(profile.temp_dir.map(text));
+        self.template_set = self.template_set.take().or(profile.template_set.clone());
+        self.output_dir = self.output_dir.take().or(profile.output_dir.map(text));
+        self.json_filepath = self
+            .json_filepath
+            .take()
+            .or(profile.json_filepath.map(text));
+        self.project_temp_dir = self
+            .project_temp_dir
+            .take()
+            .or(profile.project_temp_dir.map(text));
+        self.project_output_dir = self
+            .project_output_dir
+            .take()
+            .or(profile.project_output_dir.map(text));
+        self.compile_db = self.compile_db.take().or(profile.compile_db.map(text));
+        self.db_filter = self.db_filter.take().or(profile.db_filter.clone());
+        self.kind = self.kind.or(profile.kind);
+        self.language = self.language.or(profile.language);
+        self.data_model = self.data_model.or(profile.data_model);
+        self.encoding = self.encoding.or(profile.encoding);
+        merge_flag(
+            &mut self.preprocessed,
+            &mut self.no_preprocessed,
+            profile.preprocessed,
+        );
+        merge_flag(
+            &mut self.all_origins,
+            &mut self.no_all_origins,
+            profile.all_origins,
+        );
+        merge_flag(
+            &mut self.keep_going,
+            &mut self.no_keep_going,
+            profile.keep_going,
+        );
+        if self.include_dirs.is_empty() {
+            self.include_dirs = profile.include_dirs.into_iter().map(text).collect();
+        }
+        if self.include_fn.is_empty() {
+            self.include_fn = profile.include_fn;
+        }
+        if self.exclude_fn.is_empty() {
+            self.exclude_fn = profile.exclude_fn;
+        }
+        if self.include_var.is_empty() {
+            self.include_var = profile.include_var;
+        }
+        if self.exclude_var.is_empty() {
+            self.exclude_var = profile.exclude_var;
+        }
+        if self.lsv_macro.is_empty() {
+            self.lsv_macro = profile.lsv_macros;
+        }
+        let data = profile
+            .data
+            .into_iter()
+            .map(|(key, path)| cyagen::DataFile { key, path });
+        self.data = data.chain(self.data.drain(..)).collect();
+        let vars = profile
+            .vars
+            .into_iter()
+            .map(|(key, value)| cyagen::DataVar { key, value });
+        self.var = vars.chain(self.var.drain(..)).collect();
+        Ok(())
+    }
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
-    let code = fs::read_to_string(&args.source)
-        .with_context(|| format!("failed to open file `{}`", args.source))?;
-    let sourcename = Path::new(&args.source).with_extension("");
+    let mut args = Args::parse();
+    if let Some(Command::Templates { action }) = &args.command {
+        return run_templates(action);
+    }
+    // options not given on the command line are taken from the profile of the config file
+    let config_path = match &args.config {
+        Some(path) => Some(PathBuf::from(path)),
+        None => cyagen::Config::discover(&std::env::current_dir()?),
+    };
+    let config = config_path
+        .as_ref()
+        .map(|path| cyagen::Config::load(path))
+        .transpose()?
+        .unwrap_or_default();
+    if let Some(path) = &config_path {
+        println!("using config `{}`", path.display());
+    }
+    args.apply(config.profile(args.profile.as_deref())?)?;
+    let compile_db = args
+        .compile_db
+        .as_ref()
+        .map(|path| cyagen::CompileDb::load(Path::new(path)))
+        .transpose()?;
+    let user_data = cyagen::user_data(&args.data, &args.var)?;
+    let extra = tera::Context::from_value(user_data.clone())?;
+    // files of the template directory override the ones of the built-in template set
+    let template_set = match (&args.template_set, &args.temp_dir) {
+        (Some(name), temp_dir) => {
+            let mut set = cyagen::TemplateSet::builtin(name)?;
+            if let Some(temp_dir) = temp_dir {
+                set.overlay(cyagen::TemplateSet::load(Path::new(temp_dir))?);
+            }
+            Some(set)
+        }
+        (None, Some(temp_dir)) => Some(cyagen::TemplateSet::load(Path::new(temp_dir))?),
+        (None, None) => None,
+    };
+    let mut sources = args.source.clone();
+    if sources.is_empty() {
+        match &compile_db {
+            Some(db) => {
+                sources = db
+                    .files(args.db_filter.as_deref())?
+                    .iter()
+                    .map(|path| path.to_string_lossy().to_string())
+                    .collect()
+            }
+            None => bail!("no source given; use --source or --compile-db"),
+        }
+    }
+    let mut modules = vec![];
+    let mut failures = vec![];
+    for source in &sources {
+        let parser = run(
+            &args,
+            &config,
+            &user_data,
+            template_set.as_ref(),
+            source,
+            compile_db.as_ref(),
+            &mut failures,
+        )?;
+        if !parser.annotations.contains_key("skip") {
+            modules.push(parser);
+        }
+        if !failures.is_empty() && !args.keep_going {
+            break;
+        }
+    }
+    // render project templates once with all the modules
+    let project_temp_dir = args
+        .project_temp_dir
+        .as_ref()
+        .filter(|_| failures.is_empty() || args.keep_going);
+    if let Some(temp_dir) = project_temp_dir {
+        let Some(output_dir) = args
+            .project_output_dir
+            .as_ref()
+            .or(args.output_dir.as_ref())
+        else {
+            bail!("no output directory given for project templates");
+        };
+        let project = cyagen::Project::new(modules);
+        let set = cyagen::TemplateSet::load(Path::new(temp_dir))?;
+        let templates = cyagen::TemplateDir::from_set(&set)?;
+        report_partial_errors(&templates, &mut failures);
+        let mut bound = templates.bind_project(&project);
+        let mut render = |name: &str, _temp: &str, is_tera: bool, element: &tera::Context| {
+            if is_tera {
+                let mut extra = extra.clone();
+                extra.extend(element.clone());
+                bound.render(name, &extra)
+            } else {
+                // legacy templates of "@...@" tags have no project model
+                Err(cyagen::RenderError {
+                    template: String::new(),
+                    line: None,
+                    column: None,
+                    message:
+                        "project templates must be jinja2 templates such as .tera, .j2 or .njk"
+                            .to_string(),
+                })
+            }
+        };
+        let mut format = cyagen::TextFormat::default();
+        if let Some(cyagen::TextEncoding::Fixed(encoding)) = args.encoding {
+            format.encoding = encoding;
+        }
+        generate_files(
+            &mut render,
+            &serde_json::to_value(&project)?,
+            "project",
+            &format,
+            &set,
+            Path::new(output_dir),
+            args.keep_going,
+            &mut failures,
+        )?;
+        if failures.is_empty() {
+            println!("done!");
+        }
+    }
+    if !failures.is_empty() {
+        bail!("failed to render {} template(s)", failures.len());
+    }
+    Ok(())
+}
+
+fn run(
+    args: &Args,
+    config: &cyagen::Config,
+    user_data: &serde_json::Value,
+    template_set: Option<&cyagen::TemplateSet>,
+    source: &str,
+    compile_db: Option<&cyagen::CompileDb>,
+    failures: &mut Vec<cyagen::RenderError>,
+) -> Result<cyagen::Parser> {
+    // decode the source before parsing; its format is kept for the generated files
+    let decoded = cyagen::read_text(Path::new(source), args.encoding.unwrap_or_default())
+        .with_context(|| format!("failed to open file `{}`", source))?;
+    let code = decoded.text;
+    let sourcename = Path::new(source).with_extension("");
     let sourcename = sourcename.file_name().unwrap().to_str().unwrap();
     // parse a C file
-    let mut parser: cyagen::Parser = cyagen::Parser::parse(&code);
+    let mut options = cyagen::ParserOptions {
+        kind: args
+            .kind
+            .unwrap_or_else(|| cyagen::SourceKind::from_path(Path::new(source))),
+        language: args
+            .language
+            .unwrap_or_else(|| cyagen::Language::from_path(Path::new(source))),
+        data_model: args.data_model.unwrap_or_default(),
+        preprocessed: flag(args.preprocessed, args.no_preprocessed).unwrap_or_else(|| {
+            matches!(
+                Path::new(source).extension().and_then(|ext| ext.to_str()),
+                Some("i") | Some("ii")
+            )
+        }),
+        all_origins: args.all_origins,
+        encoding: args.encoding.unwrap_or_default(),
+        macros: args
+            .defines
+            .iter()
+            .map(|(name, value)| cyagen::MacroOp::Define(name.clone(), value.clone()))
+            .collect(),
+        extractors: config.extractors.clone(),
+        lsv_macros: args.lsv_macro.clone(),
+        ..Default::default()
+    };
+    if let Some(sourcedir) = Path::new(source).parent() {
+        options.include_paths.push(sourcedir.to_path_buf());
+    }
+    options
+        .include_paths
+        .extend(args.include_dirs.iter().map(PathBuf::from));
+    // apply flags of compile command for the source
+    if let Some(command) = compile_db.and_then(|db| db.find(Path::new(source))) {
+        let flags = command.flags();
+        options.include_paths.extend(flags.include_paths);
+        options.macros.extend(flags.macros);
+        options.forced_includes = flags.forced_includes;
+    }
+    let mut parser: cyagen::Parser = cyagen::Parser::parse_with_options(&code, &options);
     parser.sourcename = sourcename.to_string();
+    parser.json_object = user_data.clone();
+    // "// cyagen-file: skip" excludes the whole source file
+    if parser.annotations.contains_key("skip") {
+        println!("skipped `{}` by annotation", source);
+        return Ok(parser);
+    }
+    parser.filter(&cyagen::FilterSpec {
+        include_fns: args.include_fn.clone(),
+        exclude_fns: args.exclude_fn.clone(),
+        include_vars: args.include_var.clone(),
+        exclude_vars: args.exclude_var.clone(),
+    });
+    for fnc in parser
+        .fncs
+        .iter()
+        .filter(|f| args.warn_fall_through && f.may_fall_through)
+    {
+        let file = if fnc.file.is_empty() {
+            source
+        } else {
+            &fnc.file
+        };
+        eprintln!(
+            "warning: `{}` may reach the end without return ({}:{})",
+            fnc.name, file, fnc.line
+        );
+    }
     // check if json filepath specified as output
-    if let Some(json_filepath) = args.json_filepath {
+    if let Some(json_filepath) = &args.json_filepath {
+        let json_filepath = json_filepath.replace("@sourcename@", sourcename);
         let dirpath = Path::new(&json_filepath)
             .parent()
             .unwrap_or_else(|| Path::new("./"));
         if !dirpath.exists() {
-            fs::create_dir_all(&dirpath).with_context(|| {
+            fs::create_dir_all(dirpath).with_context(|| {
                 format!("failed to create folder `{}`", dirpath.to_string_lossy())
             })?;
         }
-        let sourcedirname = get_relative_path(&dirpath.to_string_lossy(), &args.source).unwrap();
+        let sourcedirname = get_relative_path(&dirpath.to_string_lossy(), source).unwrap();
         let sourcedirname = Path::new(&sourcedirname)
             .parent()
             .unwrap()
             .to_string_lossy()
             .to_string();
         parser.sourcedirname = sourcedirname;
-        let mut output_fname = format!("{}", &json_filepath);
-        if output_fname.contains("@sourcename@") {
-            output_fname = output_fname.replace("@sourcename@", &sourcename);
-        }
-        cyagen::generate_json(&parser, &output_fname)?;
+        cyagen::generate_json(&parser, &json_filepath)?;
     // check if ouput filepath specified as output
-    } else if let (Some(output_dir), Some(temp_dir)) = (args.output_dir, args.temp_dir) {
-        let sourcedirname = get_relative_path(&output_dir, &args.source).unwrap();
+    } else if let (Some(output_dir), Some(set)) = (&args.output_dir, template_set) {
+        let output_dir = output_dir.replace("@sourcename@", sourcename);
+        let sourcedirname = get_relative_path(&output_dir, source).unwrap();
         let sourcedirname = Path::new(&sourcedirname)
             .parent()
             .unwrap()
             .to_string_lossy()
             .to_string();
         parser.sourcedirname = sourcedirname;
-        let _ = generate_files(&parser, Path::new(&temp_dir), Path::new(&output_dir));
-        println!("done!");
-    } else {
+        let templates = cyagen::TemplateDir::from_set(set)?;
+        report_partial_errors(&templates, failures);
+        let extra = tera::Context::from_value(user_data.clone())?;
+        // filters, functions and the model of the source are shared by all the templates
+        let mut bound = templates.bind(&parser);
+        let mut render = |name: &str, temp: &str, is_tera: bool, element: &tera::Context| {
+            if is_tera {
+                let mut extra = extra.clone();
+                extra.extend(element.clone());
+                bound.render(name, &extra)
+            } else {
+                cyagen::generate(&parser, temp, &parser.sourcename).map(|text| Some(text.into()))
+            }
+        };
+        let count = failures.len();
+        generate_files(
+            &mut render,
+            &serde_json::to_value(&parser)?,
+            &parser.sourcename,
+            &decoded.format,
+            set,
+            Path::new(&output_dir),
+            args.keep_going,
+            failures,
+        )?;
+        if failures.len() == count {
+            println!("done!");
+        }
+    } else if args.project_temp_dir.is_none() {
         println!("wrong arguments given; you can generate json file or files based on templates at a time");
     }
-    Ok(())
+    Ok(parser)
+}
+
+/// render function of a template; template name, template text, true if it is jinja2 format
+/// and the element bound by a filename placeholder such as "@fnc.name@";
+/// `None` if skipped by the condition of the front matter
+type Render<'a> = dyn FnMut(&str, &str, bool, &tera::Context) -> Result<Option<cyagen::Rendered>, cyagen::RenderError>
+    + 'a;
+
+/// report the partials failed to load; the other templates are reported when they are rendered
+///
+fn report_partial_errors(templates: &cyagen::TemplateDir, failures: &mut Vec<cyagen::RenderError>) {
+    for (name, error) in templates.errors() {
+        if cyagen::TemplateDir::is_partial(name) {
+            eprintln!("error: {}", error);
+            failures.push(error.clone());
+        }
+    }
 }
 
-fn generate_files(parser: &cyagen::Parser, temp_dir: &Path, output_dir: &Path) -> Result<()> {
-    let mut result = Ok(());
+/// render templates into output_dir except partials prefixed with an underscore; new files are written in the given encoding
+/// with the line endings of their templates, and existing files keep their own encoding and line endings;
+/// templates failed to render are reported and added to `failures` without writing their outputs,
+/// and the rest of templates are skipped unless `keep_going`;
+/// a template is rendered once per element of the model given by its filename placeholder such as "@fnc.name@"
+///
+#[allow(clippy::too_many_arguments)]
+fn generate_files(
+    render: &mut Render,
+    model: &serde_json::Value,
+    sourcename: &str,
+    format: &cyagen::TextFormat,
+    set: &cyagen::TemplateSet,
+    output_dir: &Path,
+    keep_going: bool,
+    failures: &mut Vec<cyagen::RenderError>,
+) -> Result<()> {
     if !Path::new(&output_dir).exists() {
-        fs::create_dir_all(&output_dir)
+        fs::create_dir_all(output_dir)
             .with_context(|| format!("failed to create folder `{}`", output_dir.display()))?;
     }
-    let temp_dir = fs::read_dir(&temp_dir)
-        .with_context(|| format!("failed to read folder `{}`", temp_dir.display()))?;
-    for entry in temp_dir {
-        let entry = entry?;
-        let path = entry.path();
-        let is_file = path.is_file();
-        let temp_path = path.into_os_string().into_string().unwrap();
-        let temp_filename = temp_path.split(path::MAIN_SEPARATOR).last().unwrap();
-        if is_file {
-            let temp = fs::read_to_string(&temp_path)
-                .with_context(|| format!("failed to read file `{}`", &temp_path))?;
-            let temp_ext = Path::new(temp_filename)
-                .extension()
-                .unwrap_or_default()
-                .to_str()
-                .unwrap_or("");
-            let mut gen;
-            let mut output_fname = format!(
-                "{}{}{}",
-                output_dir.display(),
-                path::MAIN_SEPARATOR,
-                temp_filename
-            );
-            if output_fname.contains("@sourcename@") {
-                output_fname = output_fname.replace("@sourcename@", &parser.sourcename);
+    let mut output_paths = HashSet::new();
+    for (name, file) in &set.files {
+        // partials such as "_partials/" or "_macros.njk" are only included by other templates
+        if cyagen::TemplateDir::is_partial(name) {
+            continue;
+        }
+        let temp_path = file.path.to_string_lossy();
+        let output_name = name
+            .replace("@sourcename@", sourcename)
+            .replace(".tera", "")
+            .replace(".j2", "")
+            .replace(".njk", "");
+        let output_fname = output_dir.join(output_name).to_string_lossy().to_string();
+        // check if template format is jinja2 such as .tera, .j2, or .njk
+        // otherwise cyagen old style of template format
+        let is_tera = cyagen::is_tera_template(Path::new(name));
+        // legacy templates have no context to bind the element, so they are rendered once
+        let outputs = match is_tera {
+            true => cyagen::fan_out(&output_fname, model),
+            false => vec![(output_fname, None)],
+        };
+        for (output_fname, element) in outputs {
+            let mut context = tera::Context::new();
+            if let Some((name, value)) = element {
+                context.insert(name, &value);
             }
-            output_fname = output_fname
-                .replace(".tera", "")
-                .replace(".j2", "")
-                .replace(".njk", "");
-            println!("rendering ... {}", &output_fname);
-            // check if template format is jinja2 such as .tera, .j2, or .njk
-            if temp_ext == "tera" || temp_ext == "j2" || temp_ext == "njk" {
-                gen = cyagen::generate_using_tera(&parser, &temp);
-            // check if cyagen old style of template format
-            } else {
-                gen = cyagen::generate(&parser, &temp, &parser.sourcename);
+            let rendered = match render(name, &file.text, is_tera, &context) {
+                Ok(Some(rendered)) => rendered,
+                Ok(None) => {
+                    println!("skipped ... {} by condition", &output_fname);
+                    continue;
+                }
+                Err(error) => {
+                    let error = error.with_template(&temp_path);
+                    eprintln!("error: {}", error);
+                    failures.push(error);
+                    if keep_going {
+                        continue;
+                    }
+                    return Ok(());
+                }
+            };
+            // output filename given by the front matter is relative to the output directory
+            let output_path = match &rendered.output {
+                Some(output) => output_dir.join(output),
+                None => PathBuf::from(&output_fname),
+            };
+            // overloaded functions, names sanitized alike or the same output of front matters
+            // would overwrite each other
+            if !output_paths.insert(output_path.clone()) {
+                let error = cyagen::RenderError {
+                    template: temp_path.to_string(),
+                    line: None,
+                    column: None,
+                    message: format!(
+                        "`{}` is generated more than once; use a unique placeholder such as `@fnc.qualified_name@` in the filename or the output of the front matter",
+                        output_path.display()
+                    ),
+                };
+                eprintln!("error: {}", error);
+                failures.push(error);
+                if keep_going {
+                    continue;
+                }
+                return Ok(());
             }
-            // check if output file is already existed, then merge with manual sections
-            if PathBuf::from(&output_fname).exists() {
-                let old_gen = fs::read_to_string(&output_fname)
-                    .with_context(|| format!("failed to read file `{}`", &output_fname))?;
-                gen = cyagen::merge_with_manual_sections(&gen, &old_gen);
+            if output_path.exists() && rendered.overwrite == cyagen::Overwrite::Never {
+                println!("kept ... {}", output_path.display());
+                continue;
+            }
+            println!("rendering ... {}", output_path.display());
+            let mut gen = rendered.text;
+            let mut output_format = cyagen::TextFormat {
+                line_ending: file.line_ending,
+                ..*format
+            };
+            // existing files keep their own encoding and line endings
+            if output_path.exists() {
+                let old_gen =
+                    cyagen::read_text(&output_path, cyagen::TextEncoding::Fixed(format.encoding))?;
+                // check if output file is already existed, then merge with manual sections
+                if rendered.overwrite == cyagen::Overwrite::Merge {
+                    gen = cyagen::merge_with_manual_sections(&gen, &old_gen.text);
+                }
+                output_format = old_gen.format;
+            } else if let Some(dirpath) = output_path.parent() {
+                fs::create_dir_all(dirpath)
+                    .with_context(|| format!("failed to create folder `{}`", dirpath.display()))?;
             }
             //
-            fs::write(&output_fname, gen)
-                .with_context(|| format!("failed to write file `{}`", &output_fname))?;
-        } else {
-            let output_path =
-                output_dir.join(&temp_filename.replace("@sourcename@", &parser.sourcename));
-            result = generate_files(&parser, Path::new(&temp_path), &output_path);
+            cyagen::write_text(&output_path, &gen, &output_format)?;
+        }
+    }
+    Ok(())
+}
+
+/// list or export the built-in template sets
+///
+fn run_templates(action: &TemplatesAction) -> Result<()> {
+    match action {
+        TemplatesAction::List => {
+            for name in cyagen::TemplateSet::builtin_names() {
+                println!("{}", name);
+            }
+        }
+        TemplatesAction::Export { name, dir } => {
+            let dir = Path::new(dir);
+            if dir
+                .read_dir()
+                .is_ok_and(|mut entries| entries.next().is_some())
+            {
+                bail!("folder `{}` is not empty", dir.display());
+            }
+            cyagen::TemplateSet::builtin(name)?.export(dir)?;
+            println!("exported `{}` to `{}`", name, dir.display());
         }
     }
-    result
+    Ok(())
 }
 
 fn get_relative_path(from_pathstr: &str, to_pathstr: &str) -> Option<String> {
-    let to_path = Path::new(to_pathstr).to_path_buf();
-    let from_path = Path::new(from_pathstr).to_path_buf();
+    let mut to_path = Path::new(to_pathstr).to_path_buf();
+    let mut from_path = Path::new(from_pathstr).to_path_buf();
+    // both paths should be absolute when either one is absolute
+    if to_path.is_absolute() != from_path.is_absolute() {
+        let current_dir = std::env::current_dir().ok()?;
+        to_path = current_dir.join(to_path);
+        from_path = current_dir.join(from_path);
+    }
 
     let common_prefix = to_path
         .components()
@@ -195,7 +740,7 @@ int func2(char c);
 
 ";
         let parser = cyagen::Parser::parse(code);
-        let gen = cyagen::generate(&parser, temp, sourcename);
+        let gen = cyagen::generate(&parser, temp, sourcename).unwrap();
         assert_eq!(gen, expected);
     }
 
@@ -241,7 +786,22 @@ int func1();
 int func2(char c);
 ";
         let parser = cyagen::Parser::parse(code);
-        let gen = cyagen::generate_using_tera(&parser, temp);
+        let gen = cyagen::generate_using_tera(&parser, temp).unwrap();
         assert_eq!(gen, expected);
     }
+
+    #[test]
+    fn test_apply_profile() {
+        use super::Args;
+        use clap::Parser;
+        let profile: cyagen::Profile = toml::from_str("keep_going = true").unwrap();
+        // the profile value is taken unless the flag is given
+        let mut args = Args::try_parse_from(["cyagen"]).unwrap();
+        args.apply(profile.clone()).unwrap();
+        assert!(args.keep_going);
+        // "--no-" flag overrides the profile value
+        let mut args = Args::try_parse_from(["cyagen", "--no-keep-going"]).unwrap();
+        args.apply(profile).unwrap();
+        assert!(!args.keep_going);
+    }
 }
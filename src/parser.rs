@@ -1,12 +1,24 @@
+use super::encoding::{read_text, TextEncoding};
+use super::expr::{ConstTable, DataModel};
+use super::extractor::{Extraction, Extractor, ExtractorScope};
+use super::initializer::Initializer;
+use super::linemap::{LineCounter, LineMap, OffsetMap};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_json;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::LazyLock;
 
 /// identifiers in the list of "include"
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Include {
     /// captured string by regex; ex) "#include <stdio.h>"
     pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
 }
 
 /// identifiers in the list of "typedef"
@@ -14,6 +26,10 @@ pub struct Include {
 pub struct Typedefs {
     /// captured string by regex; ex) "typedef enum\n{\n    Idle = 0,\n    Forward,\n    TurnLeft,\n    TurnRight,\n    MaxDirection\n} Direction_t;"
     pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
 }
 
 /// identifiers in the list of "static_vars"
@@ -21,6 +37,10 @@ pub struct Typedefs {
 pub struct StaticVariable {
     /// captured string by regex; ex) "static int array_var\[10\] = { 1, 2, 3 };"
     pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
     /// variable name expression; ex) "array_var\[10\]"
     pub name_expr: String,
     /// variable name; ex) "array_var"
@@ -33,10 +53,95 @@ pub struct StaticVariable {
     pub func_name: String,
     /// init value; ex) "{ 1, 2, 3 }"
     pub init: String,
-    /// array size; ex) "10"
-    pub array_size: i32,
+    /// structured init value; scalars, nested lists, designators and string literals
+    pub initializer: Initializer,
+    /// array size expression as written; ex) "BUF_LEN * 2"
+    pub array_size_expr: String,
+    /// evaluated array size; ex) 10, or None if not an array or not resolvable
+    pub array_size: Option<i32>,
     /// true if the variable is const
     pub is_const: bool,
+    /// macro declaring the variable; ex) "LOCAL_STATIC_VARIABLE", or empty if declared with "static"
+    pub lsv_macro: String,
+    /// active memory section; ex) "VAR_CLEARED_8" for "MOTOR_START_SEC_VAR_CLEARED_8"
+    pub section: String,
+    /// annotations given by a "cyagen:" comment; ex) {"mock": "real", "tag": "safety"}
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// identifiers in the list of "locals" of a function
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalVariable {
+    /// captured declaration; ex) "uint32_t elapsed = now - startTime"
+    pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
+    /// variable name expression; ex) "buf\[4\]"
+    pub name_expr: String,
+    /// variable name; ex) "elapsed"
+    pub name: String,
+    /// data type; ex) "uint32_t"
+    pub dtype: String,
+    /// init value; ex) "now - startTime", or empty if not initialized
+    pub init: String,
+    /// array size expression as written; ex) "4"
+    pub array_size_expr: String,
+    /// evaluated array size; ex) 4, or None if not an array or not resolvable
+    pub array_size: Option<i32>,
+    /// true if the variable is const
+    pub is_const: bool,
+}
+
+/// kind of a returned expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReturnKind {
+    /// no expression; ex) "return;"
+    Void,
+    /// literal or constant expression including macros; ex) "0", "-1", "E_OK" or "\"ok\""
+    Literal,
+    /// enum constant; ex) "Forward"
+    EnumConstant,
+    /// variable, member or array element; ex) "speed", "motor->speed" or "buf\[0\]"
+    Variable,
+    /// result of a function call; ex) "getCurrentTime()"
+    Call,
+    /// any other expression; ex) "a + b"
+    Expression,
+}
+
+/// identifiers in the list of "returns" of a function
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnInfo {
+    /// captured string by regex; ex) "return getCurrentTime() - startTime;"
+    pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
+    /// returned expression; ex) "getCurrentTime() - startTime"
+    pub expr: String,
+    /// kind of the expression; void, literal, enum_constant, variable, call or expression
+    pub kind: ReturnKind,
+    /// evaluated value of literal and enum constant; ex) 0
+    pub value: Option<i64>,
+    /// called function name if the kind is call; ex) "getCurrentTime"
+    pub callee: String,
+}
+
+/// range of text in the parsed source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    /// byte offset of the start
+    pub start: usize,
+    /// byte offset of the end; exclusive
+    pub end: usize,
+    /// line number of the start
+    pub start_line: usize,
+    /// line number of the end
+    pub end_line: usize,
 }
 
 /// identifiers in the list of "fncs"
@@ -44,6 +149,10 @@ pub struct StaticVariable {
 pub struct Function {
     /// captured string by regex; ex) "void setDir(const Direction_t dir)\n{"
     pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
     /// name; ex) "setDir"
     pub name: String,
     /// true if the function is static; ex) false
@@ -56,6 +165,156 @@ pub struct Function {
     pub atypes: String,
     /// argument names; ex) "dir"
     pub anames: String,
+    /// true if the function is declared inline; ex) false
+    pub is_inline: bool,
+    /// true if the function is declared in a header as public API; ex) false
+    pub is_public: bool,
+    /// name qualified with namespaces and classes; ex) "drv::Motor::setDir"
+    pub qualified_name: String,
+    /// enclosing namespaces joined with "::"; ex) "drv"
+    pub namespace: String,
+    /// class of a member function; nested classes are joined with "::"; ex) "Motor"
+    pub class: String,
+    /// true if the function is an interrupt handler; ex) "ISR(TIMER0_vect)" or "__interrupt void tick(void)"
+    pub is_isr: bool,
+    /// active memory section; ex) "CODE" for "MOTOR_START_SEC_CODE", or ".text_fast" for "#pragma section .text_fast"
+    pub section: String,
+    /// function body including braces and comments; ex) "{\n    return speed; // current\n}"
+    pub body: String,
+    /// range of the body in the source; None if the function has no body
+    pub body_span: Option<Span>,
+    /// non-static local variables declared in the body
+    pub locals: Vec<LocalVariable>,
+    /// return statements in the body
+    pub returns: Vec<ReturnInfo>,
+    /// true if the function returns a value but the end of the body may be reached without return
+    pub may_fall_through: bool,
+    /// annotations given by a "cyagen:" comment; ex) {"mock": "real", "tag": "safety"}
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// identifiers in the list of "extern_vars"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExternVariable {
+    /// captured string by regex; ex) "extern uint8_t rxBuf\[RX_LEN\];"
+    pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
+    /// variable name expression; ex) "rxBuf\[RX_LEN\]"
+    pub name_expr: String,
+    /// variable name; ex) "rxBuf"
+    pub name: String,
+    /// data type; ex) "uint8_t"
+    pub dtype: String,
+    /// array size expression as written; ex) "RX_LEN"
+    pub array_size_expr: String,
+    /// evaluated array size; ex) 16, or None if not an array or not resolvable
+    pub array_size: Option<i32>,
+    /// true if the variable is const
+    pub is_const: bool,
+    /// true if the variable is declared in a header as public API
+    pub is_public: bool,
+    /// annotations given by a "cyagen:" comment; ex) {"mock": "real", "tag": "safety"}
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// identifiers in the list of "global_vars"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GlobalVariable {
+    /// captured string by regex; ex) "uint8_t motorPins\[MOTOR_COUNT\] = { 10, 11 };"
+    pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
+    /// variable name expression; ex) "motorPins\[MOTOR_COUNT\]"
+    pub name_expr: String,
+    /// variable name; ex) "motorPins"
+    pub name: String,
+    /// data type; ex) "uint8_t"
+    pub dtype: String,
+    /// init value; ex) "{ 10, 11 }", or empty if not initialized
+    pub init: String,
+    /// array size expression as written; ex) "MOTOR_COUNT"
+    pub array_size_expr: String,
+    /// evaluated array size; ex) 2, or None if not an array or not resolvable
+    pub array_size: Option<i32>,
+    /// true if the variable is const
+    pub is_const: bool,
+    /// active memory section; ex) "VAR_INIT_UNSPECIFIED"
+    pub section: String,
+    /// annotations given by a "cyagen:" comment; ex) {"mock": "real", "tag": "safety"}
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// identifiers in the list of "macros"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Macro {
+    /// captured string by regex; ex) "#define MAX(a, b) ((a) > (b) ? (a) : (b))"
+    pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
+    /// name; ex) "MAX"
+    pub name: String,
+    /// true if the macro takes parameters
+    pub is_function_like: bool,
+    /// parameter names; ex) \["a", "b"\]
+    pub params: Vec<String>,
+    /// replacement text; ex) "((a) > (b) ? (a) : (b))"
+    pub value: String,
+    /// evaluated value if the replacement text is a constant expression; ex) None
+    pub int_value: Option<i64>,
+    /// true if the macro is defined in a header as public API
+    pub is_public: bool,
+}
+
+/// identifiers in the list of "pragmas"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pragma {
+    /// captured string by regex; ex) "#pragma vector=TIMER0_A0_VECTOR"
+    pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
+    /// pragma text without "#pragma"; ex) "vector=TIMER0_A0_VECTOR"
+    pub text: String,
+    /// function where the pragma is placed; empty at file scope
+    pub func_name: String,
+}
+
+/// identifiers in the list of "asm_blocks"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AsmBlock {
+    /// captured string by regex; ex) "__asm__ volatile (\"nop\");"
+    pub captured: String,
+    /// original file of the element; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the element in the original file
+    pub line: usize,
+    /// assembly within parentheses or braces; ex) "\"nop\""
+    pub code: String,
+    /// function where the assembly is placed; empty at file scope
+    pub func_name: String,
+}
+
+/// identifiers in the list of "ext_calls"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExternalCall {
+    /// name of the called function which is not defined in the source file; ex) "controlPin"
+    pub name: String,
+    /// original file of the call; empty unless the input is preprocessed
+    pub file: String,
+    /// line number of the call in the original file
+    pub line: usize,
+    /// function where the call is made
+    pub caller: Function,
+    /// prototype of the called function if declared in the source file
+    pub proto: Option<Function>,
 }
 
 /// identifiers in the list of "ncls"
@@ -65,61 +324,802 @@ pub struct NestedCall {
     pub caller: Function,
 }
 
+/// kind of the file to be parsed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// C source file; functions are recognized by their definitions
+    #[default]
+    Source,
+    /// C header file; prototypes and declarations are treated as public API
+    Header,
+}
+
+impl SourceKind {
+    /// kind of the file detected by its extension
+    ///
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("h") | Some("hh") | Some("hpp") | Some("hxx") => SourceKind::Header,
+            _ => SourceKind::Source,
+        }
+    }
+}
+
+impl FromStr for SourceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "source" => Ok(SourceKind::Source),
+            "header" => Ok(SourceKind::Header),
+            _ => Err(format!("unknown kind `{}`; expected source or header", s)),
+        }
+    }
+}
+
+/// language of the source file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// C; functions are plain names
+    #[default]
+    C,
+    /// C++; namespaces, classes and member functions are recognized
+    Cpp,
+}
+
+impl Language {
+    /// language of the file detected by its extension; ".h" is regarded as C
+    ///
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("cpp") | Some("cc") | Some("cxx") | Some("c++") | Some("hpp") | Some("hh")
+            | Some("hxx") | Some("ii") => Language::Cpp,
+            _ => Language::C,
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "c" => Ok(Language::C),
+            "cpp" | "c++" => Ok(Language::Cpp),
+            _ => Err(format!("unknown language `{}`; expected c or cpp", s)),
+        }
+    }
+}
+
+/// argument of a local static variable macro
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LsvArg {
+    /// function name; "func"
+    Func,
+    /// data type; "type"
+    Type,
+    /// variable name with optional array size; "name"
+    Name,
+    /// initial value; "init"
+    Init,
+    /// ignored argument; "_"
+    Ignore,
+}
+
+/// signature of a macro defining a local static variable;
+/// ex) "LOCAL_STATIC_VARIABLE(func, type, name, init)" or "TESTABLE_STATIC(type, name, init)"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LsvMacro {
+    /// macro name; ex) "LOCAL_STATIC_VARIABLE"
+    pub name: String,
+    /// layout of the arguments
+    pub args: Vec<LsvArg>,
+}
+
+impl Default for LsvMacro {
+    fn default() -> Self {
+        Self {
+            name: "LOCAL_STATIC_VARIABLE".to_string(),
+            args: vec![LsvArg::Func, LsvArg::Type, LsvArg::Name, LsvArg::Init],
+        }
+    }
+}
+
+impl FromStr for LsvMacro {
+    type Err = String;
+
+    /// a macro name only has the default layout "(func, type, name, init)"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let re = Regex::new(r"^\s*(?<name>[A-Za-z_]\w*)\s*(?:\((?<args>[^)]*)\))?\s*$").unwrap();
+        let cap = re.captures(s).ok_or_else(|| {
+            format!(
+                "invalid macro signature `{}`; ex) TESTABLE_STATIC(type, name, init)",
+                s
+            )
+        })?;
+        let Some(args) = cap.name("args") else {
+            return Ok(Self {
+                name: cap["name"].to_string(),
+                ..Default::default()
+            });
+        };
+        let args = args
+            .as_str()
+            .split(',')
+            .map(|arg| match arg.trim() {
+                "func" => Ok(LsvArg::Func),
+                "type" => Ok(LsvArg::Type),
+                "name" => Ok(LsvArg::Name),
+                "init" => Ok(LsvArg::Init),
+                "_" => Ok(LsvArg::Ignore),
+                arg => Err(format!(
+                    "unknown argument `{}` of `{}`; expected func, type, name, init or _",
+                    arg, s
+                )),
+            })
+            .collect::<Result<Vec<LsvArg>, String>>()?;
+        if !args.contains(&LsvArg::Type) || !args.contains(&LsvArg::Name) {
+            return Err(format!("`{}` needs type and name arguments", s));
+        }
+        Ok(Self {
+            name: cap["name"].to_string(),
+            args,
+        })
+    }
+}
+
+impl TryFrom<String> for LsvMacro {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<LsvMacro> for String {
+    fn from(m: LsvMacro) -> Self {
+        let args: Vec<&str> = m.args.iter().map(|arg| arg.keyword()).collect();
+        format!("{}({})", m.name, args.join(", "))
+    }
+}
+
+impl LsvArg {
+    /// keyword of the argument in a macro signature
+    ///
+    fn keyword(&self) -> &'static str {
+        match self {
+            LsvArg::Func => "func",
+            LsvArg::Type => "type",
+            LsvArg::Name => "name",
+            LsvArg::Init => "init",
+            LsvArg::Ignore => "_",
+        }
+    }
+}
+
+/// identifiers in the list of "lsv_macros"; argument layout of a local static variable macro
+/// to redefine the macro in templates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LsvLayout {
+    /// macro name; ex) "TESTABLE_STATIC"
+    pub name: String,
+    /// arguments by position; "func", "type", "name", "init" or "_"; ex) \["type", "name", "init"\]
+    pub args: Vec<String>,
+    /// parameters to define the macro, named "funcname", "datatype", "varname", "initvalue" or "unused<N>";
+    /// ex) "datatype, varname, initvalue"
+    pub params: String,
+    /// true if the function name is given as an argument
+    pub has_func: bool,
+}
+
+impl From<&LsvMacro> for LsvLayout {
+    fn from(m: &LsvMacro) -> Self {
+        let params: Vec<String> = m
+            .args
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| match arg {
+                LsvArg::Func => "funcname".to_string(),
+                LsvArg::Type => "datatype".to_string(),
+                LsvArg::Name => "varname".to_string(),
+                LsvArg::Init => "initvalue".to_string(),
+                LsvArg::Ignore => format!("unused{}", i),
+            })
+            .collect();
+        Self {
+            name: m.name.clone(),
+            args: m.args.iter().map(|arg| arg.keyword().to_string()).collect(),
+            params: params.join(", "),
+            has_func: m.args.contains(&LsvArg::Func),
+        }
+    }
+}
+
+impl LsvMacro {
+    /// regex to capture the macro call with the groups "fnc_name", "dtype", "name", "array_size" and "value"
+    ///
+    fn regex(&self) -> Regex {
+        let args: Vec<&str> = self
+            .args
+            .iter()
+            .map(|arg| match arg {
+                LsvArg::Func => r"\s*(?<fnc_name>\w+)\s*",
+                LsvArg::Type => r"\s*(?<dtype>.*?)\s*",
+                LsvArg::Name => r"\s*(?<name>\w+)\s*(?:\[(?<array_size>.*?)\])?\s*",
+                LsvArg::Init => r"\s*(?<value>.*?)\s*",
+                LsvArg::Ignore => r"[^,]*?",
+            })
+            .collect();
+        Regex::new(&format!(
+            r"\b{}\s*\({}\).*?;",
+            regex::escape(&self.name),
+            args.join(",")
+        ))
+        .unwrap()
+    }
+}
+
+/// macro defined with `-D` or undefined with `-U`
+#[derive(Debug, Clone, PartialEq)]
+pub enum MacroOp {
+    /// (name, value); ex) `-DBUF_LEN=64`
+    Define(String, String),
+    /// name; ex) `-UNDEBUG`
+    Undefine(String),
+}
+
+/// options to control parsing
+#[derive(Debug, Clone, Default)]
+pub struct ParserOptions {
+    /// kind of the file to be parsed
+    pub kind: SourceKind,
+    /// language of the file to be parsed
+    pub language: Language,
+    /// target data model to evaluate `sizeof` in constant expressions
+    pub data_model: DataModel,
+    /// directories to search included headers for `#define` and enum constants
+    pub include_paths: Vec<PathBuf>,
+    /// macros defined or undefined before parsing such as `-D` and `-U`, applied in order
+    pub macros: Vec<MacroOp>,
+    /// headers included before the source such as `-include`
+    pub forced_includes: Vec<PathBuf>,
+    /// true if the text is preprocessor output with line markers such as `gcc -E`
+    pub preprocessed: bool,
+    /// true to keep the elements of preprocessed text originating from included files;
+    /// only the elements of the primary source are kept by default
+    pub all_origins: bool,
+    /// character encoding of the headers read to collect constants
+    pub encoding: TextEncoding,
+    /// user defined regex extractors; the matches are listed in `extracted` by name
+    pub extractors: Vec<Extractor>,
+    /// macros defining local static variables; "LOCAL_STATIC_VARIABLE(func, type, name, init)" if empty
+    pub lsv_macros: Vec<LsvMacro>,
+}
+
 /// identifiers which can be used in template
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Parser {
+    /// user data given by `--data` and `--var` by key; ex) {"owner": "motor-team"}
     pub json_object: serde_json::Value,
     /// source filename without extension to be parsed
     pub sourcename: String,
     /// source file folder
     pub sourcedirname: String,
-    /// local static variable macro name; LOCAL_STATIC_VARIABLE
+    /// kind of the parsed file; "source" or "header"
+    pub kind: SourceKind,
+    /// language of the parsed file; "c" or "cpp"
+    pub language: Language,
+    /// local static variable macro name; the first one if several are given; ex) "LOCAL_STATIC_VARIABLE"
     pub lsv_macro_name: String,
+    /// local static variable macros with their argument layouts
+    pub lsv_macros: Vec<LsvLayout>,
     /// list of "include"
     pub incs: Vec<Include>,
-    /// list of "typedef"
+    /// list of "typedef" and tagged struct, union and enum definitions
     pub typedefs: Vec<Typedefs>,
+    /// list of "#define"
+    pub macros: Vec<Macro>,
     /// list of static variables
     pub static_vars: Vec<StaticVariable>,
-    /// list of functions
+    /// list of extern variables
+    pub extern_vars: Vec<ExternVariable>,
+    /// list of non-static variables defined at file scope
+    pub global_vars: Vec<GlobalVariable>,
+    /// list of functions; in header mode, prototypes without definition are included
     pub fncs: Vec<Function>,
+    /// list of function prototypes declared at file scope
+    pub protos: Vec<Function>,
     /// list of nested calls
     pub ncls: Vec<NestedCall>,
     /// list of functions called within the source file
     pub callees: Vec<Function>,
+    /// list of calls to functions which are not defined in the source file
+    pub ext_calls: Vec<ExternalCall>,
+    /// list of "#pragma"
+    pub pragmas: Vec<Pragma>,
+    /// list of inline assembly such as `asm("nop");` or `__asm { ... }`
+    pub asm_blocks: Vec<AsmBlock>,
+    /// file level annotations given by "cyagen-file:" comments; ex) {"skip": "true"}
+    pub annotations: BTreeMap<String, String>,
+    /// lists of the matches of user defined extractors by name; ex) {"dem_events": [...]}
+    pub extracted: BTreeMap<String, Vec<Extraction>>,
 }
 
 impl Parser {
     /// parse the given textdata and return Parse object to be used for generator
     ///
     pub fn parse(textdata: &str) -> Self {
-        let code = remove_comments(textdata);
-        let fncs = get_fncs(&code);
+        Self::parse_with_options(textdata, &ParserOptions::default())
+    }
+
+    /// parse the given textdata with options and return Parse object to be used for generator
+    ///
+    pub fn parse_with_options(textdata: &str, options: &ParserOptions) -> Self {
+        let (mut code, offsets) = remove_comments_with_offsets(textdata);
+        let mut original = Some((textdata, &offsets));
+        let mut linemap = None;
+        if options.preprocessed {
+            let (map, text) = LineMap::from_preprocessed(&code);
+            code = text;
+            linemap = Some(map);
+            // markers are blanked, so the body is taken from the parsed text
+            original = None;
+        }
+        let is_public = options.kind == SourceKind::Header;
+        let consts = get_consts(&code, options);
+        let mut fncs = match options.language {
+            Language::C => get_fncs(&code),
+            Language::Cpp => get_cpp_fncs(&code),
+        };
+        fncs.extend(get_isr_macro_fncs(&code));
+        fncs.sort_by_key(|f| f.line);
+        let bodies = get_bodies(&code, &fncs);
+        let pragmas = get_pragmas(&code, &bodies);
+        mark_isrs(&mut fncs, &pragmas);
+        let sections = get_sections(&code);
+        for fnc in fncs.iter_mut() {
+            fnc.section = section_at(&sections, fnc.line);
+        }
+        update_fncs_with_body(&code, original, &consts, &mut fncs);
+        let asm_blocks = get_asm_blocks(&code, &bodies);
+        let extracted = get_extractions(&code, original, &bodies, &options.extractors);
         let ncls = get_ncls(&code, &fncs);
         let callees: Vec<Function> = get_callees(&ncls);
-        let mut static_vars = get_static_vars(&code, &fncs);
-        let lsv_macro_name = "LOCAL_STATIC_VARIABLE".to_string();
-        update_static_vars_with_lsv(&code, &fncs, &lsv_macro_name, &mut static_vars);
-        Self {
+        let mut static_vars = get_static_vars(&code, &fncs, &consts);
+        let lsv_macros = if options.lsv_macros.is_empty() {
+            vec![LsvMacro::default()]
+        } else {
+            options.lsv_macros.clone()
+        };
+        for lsv_macro in &lsv_macros {
+            update_static_vars_with_lsv(&code, &fncs, lsv_macro, &consts, &mut static_vars);
+        }
+        let lsv_macro_name = lsv_macros[0].name.clone();
+        let mut protos = get_protos(&code);
+        let mut extern_vars = get_extern_vars(&code, &consts);
+        let mut global_vars = get_global_vars(&code, &consts);
+        let mut macros = get_macros(&code, &consts);
+        let ext_calls = get_ext_calls(&code, &fncs, &protos, &macros, &lsv_macros);
+        let mut typedefs = get_typedefs(&code);
+        typedefs.extend(get_tagged_types(&code));
+        for var in static_vars.iter_mut() {
+            var.section = section_at(&sections, var.line);
+        }
+        for var in global_vars.iter_mut() {
+            var.section = section_at(&sections, var.line);
+        }
+        if is_public {
+            fncs.iter_mut().for_each(|f| f.is_public = true);
+            protos.iter_mut().for_each(|f| f.is_public = true);
+            extern_vars.iter_mut().for_each(|v| v.is_public = true);
+            macros.iter_mut().for_each(|m| m.is_public = true);
+            // prototypes without inline definition are the functions of a header
+            for proto in &protos {
+                if !find_func_in_list(&proto.name, &fncs) {
+                    fncs.push(proto.clone());
+                }
+            }
+        }
+        let mut parser = Self {
             json_object: serde_json::json!({}),
             sourcename: String::new(),
             sourcedirname: String::new(),
-            lsv_macro_name: lsv_macro_name,
+            kind: options.kind,
+            language: options.language,
+            lsv_macro_name,
+            lsv_macros: lsv_macros.iter().map(LsvLayout::from).collect(),
             incs: get_incs(&code),
-            typedefs: get_typedefs(&code),
-            static_vars: static_vars,
-            fncs: fncs.clone(),
-            ncls: ncls,
-            callees: callees,
+            typedefs,
+            macros,
+            static_vars,
+            extern_vars,
+            global_vars,
+            fncs,
+            protos,
+            ncls,
+            callees,
+            ext_calls,
+            pragmas,
+            asm_blocks,
+            annotations: BTreeMap::new(),
+            extracted,
+        };
+        parser.annotate(textdata, &code);
+        if let Some(map) = linemap {
+            parser.relocate(&map, !options.all_origins);
+        }
+        parser
+    }
+
+    /// attach annotations of "cyagen:" comments to the elements, and remove the elements annotated with "skip";
+    /// an annotation belongs to the element on the same line, otherwise to the element which follows it
+    ///
+    fn annotate(&mut self, text: &str, code: &str) {
+        let (file_level, annotations) = get_annotations(text);
+        self.annotations = file_level;
+        let code_lines: Vec<&str> = code.lines().collect();
+        let mut element_lines: Vec<usize> = self
+            .fncs
+            .iter()
+            .map(|f| f.line)
+            .chain(self.protos.iter().map(|f| f.line))
+            .chain(self.static_vars.iter().map(|v| v.line))
+            .chain(self.global_vars.iter().map(|v| v.line))
+            .chain(self.extern_vars.iter().map(|v| v.line))
+            .collect();
+        element_lines.sort();
+        for (line, is_inline, map) in annotations {
+            let target = if is_inline {
+                line
+            } else {
+                match element_lines.iter().find(|l| **l >= line) {
+                    Some(l) => *l,
+                    None => continue,
+                }
+            };
+            // only blank lines and directives are allowed between the annotation and the element
+            let is_adjacent = (line + 1..target).all(|l| {
+                let text = code_lines.get(l - 1).map_or("", |t| t.trim());
+                text.is_empty() || text.starts_with('#')
+            });
+            if !is_adjacent {
+                continue;
+            }
+            macro_rules! annotate {
+                ($list:expr) => {
+                    for item in $list.iter_mut().filter(|item| item.line == target) {
+                        item.annotations.extend(map.clone());
+                    }
+                };
+            }
+            annotate!(self.fncs);
+            annotate!(self.protos);
+            annotate!(self.static_vars);
+            annotate!(self.global_vars);
+            annotate!(self.extern_vars);
+        }
+        // copies of functions get the annotations as well
+        let annotations_of = |name: &str| -> BTreeMap<String, String> {
+            self.fncs
+                .iter()
+                .find(|f| f.name == name)
+                .map_or(BTreeMap::new(), |f| f.annotations.clone())
+        };
+        for ncl in self.ncls.iter_mut() {
+            ncl.caller.annotations = annotations_of(&ncl.caller.name);
+            ncl.callee.annotations = annotations_of(&ncl.callee.name);
+        }
+        for call in self.ext_calls.iter_mut() {
+            call.caller.annotations = annotations_of(&call.caller.name);
+        }
+        // elements annotated with "skip" are removed with their local variables and calls
+        let is_skipped = |annotations: &BTreeMap<String, String>| annotations.contains_key("skip");
+        let skipped: Vec<String> = self
+            .fncs
+            .iter()
+            .filter(|f| is_skipped(&f.annotations))
+            .map(|f| f.name.clone())
+            .collect();
+        self.protos.retain(|f| !is_skipped(&f.annotations));
+        self.static_vars.retain(|v| !is_skipped(&v.annotations));
+        self.global_vars.retain(|v| !is_skipped(&v.annotations));
+        self.extern_vars.retain(|v| !is_skipped(&v.annotations));
+        self.retain(|name| !skipped.iter().any(|s| s == name), |_| true);
+    }
+
+    /// keep the functions and variables of which names are accepted; local static variables, extracted items,
+    /// pragmas and assembly blocks of removed functions, and calls from or to removed functions are removed as well;
+    /// **locals** and **returns** are removed with their function
+    ///
+    pub(crate) fn retain(
+        &mut self,
+        keep_fn: impl Fn(&str) -> bool,
+        keep_var: impl Fn(&str) -> bool,
+    ) {
+        let removed: Vec<String> = self
+            .fncs
+            .iter()
+            .filter(|f| !keep_fn(&f.name))
+            .map(|f| f.name.clone())
+            .collect();
+        self.fncs.retain(|f| keep_fn(&f.name));
+        self.protos.retain(|f| keep_fn(&f.name));
+        self.static_vars
+            .retain(|v| keep_var(&v.name) && !removed.contains(&v.func_name));
+        self.global_vars.retain(|v| keep_var(&v.name));
+        self.extern_vars.retain(|v| keep_var(&v.name));
+        self.ncls
+            .retain(|n| keep_fn(&n.caller.name) && keep_fn(&n.callee.name));
+        self.callees = get_callees(&self.ncls);
+        self.ext_calls
+            .retain(|c| keep_fn(&c.name) && keep_fn(&c.caller.name));
+        for list in self.extracted.values_mut() {
+            list.retain(|item| !removed.contains(&item.func_name));
+        }
+        self.pragmas.retain(|p| !removed.contains(&p.func_name));
+        self.asm_blocks.retain(|a| !removed.contains(&a.func_name));
+    }
+
+    /// replace the line numbers of preprocessed text with the original file and line,
+    /// and drop the elements not originating from the primary source if `primary_only`
+    ///
+    fn relocate(&mut self, map: &LineMap, primary_only: bool) {
+        macro_rules! relocate {
+            ($list:expr) => {
+                for item in $list.iter_mut() {
+                    (item.file, item.line) = map.origin(item.line);
+                }
+                if primary_only {
+                    $list.retain(|item| item.file == map.primary_file());
+                }
+            };
+        }
+        relocate!(self.incs);
+        relocate!(self.typedefs);
+        relocate!(self.macros);
+        relocate!(self.static_vars);
+        relocate!(self.extern_vars);
+        for fnc in self.fncs.iter_mut() {
+            if let Some(span) = fnc.body_span.as_mut() {
+                span.start_line = map.origin(span.start_line).1;
+                span.end_line = map.origin(span.end_line).1;
+            }
+            relocate!(fnc.locals);
+            relocate!(fnc.returns);
+        }
+        relocate!(self.fncs);
+        relocate!(self.protos);
+        relocate!(self.global_vars);
+        relocate!(self.pragmas);
+        relocate!(self.asm_blocks);
+        for list in self.extracted.values_mut() {
+            for item in list.iter_mut() {
+                item.span.start_line = map.origin(item.span.start_line).1;
+                item.span.end_line = map.origin(item.span.end_line).1;
+            }
+            relocate!(list);
+        }
+        for call in self.ext_calls.iter_mut() {
+            (call.caller.file, call.caller.line) = map.origin(call.caller.line);
+        }
+        relocate!(self.ext_calls);
+        for ncl in self.ncls.iter_mut() {
+            (ncl.caller.file, ncl.caller.line) = map.origin(ncl.caller.line);
+            (ncl.callee.file, ncl.callee.line) = map.origin(ncl.callee.line);
+        }
+        if primary_only {
+            self.ncls
+                .retain(|ncl| ncl.caller.file == map.primary_file());
+        }
+        // callees are derived from the nested calls to be consistent with them
+        self.callees = get_callees(&self.ncls);
+    }
+}
+
+/// annotations of "cyagen:" comments such as "// cyagen: skip" or "/* cyagen: mock=real tag=safety */";
+/// (file level annotations of "cyagen-file:", list of (line, true if code precedes it, annotations))
+///
+#[allow(clippy::type_complexity)]
+fn get_annotations(
+    text: &str,
+) -> (
+    BTreeMap<String, String>,
+    Vec<(usize, bool, BTreeMap<String, String>)>,
+) {
+    let re =
+        Regex::new(r"(?m)(?://|/\*)[ \t]*cyagen(?<file>-file)?:(?<body>.*?)(?:\*/|$)").unwrap();
+    let re4item =
+        Regex::new(r#"(?<key>[\w.-]+)(?:\s*=\s*(?:"(?<quoted>[^"]*)"|(?<value>[^\s,"]+)))?"#)
+            .unwrap();
+    let mut file_level = BTreeMap::new();
+    let mut result = vec![];
+    let mut lines = LineCounter::new(text);
+    // annotations are only taken from comments; ex) not from "printf(\"// cyagen: skip\")"
+    let captures = get_comment_spans(text)
+        .into_iter()
+        .flat_map(|(begin, end)| {
+            re.captures_iter(text.get(begin..end).unwrap())
+                .map(move |cap| (begin, cap))
+        });
+    for (begin, cap) in captures {
+        let start = begin + cap.get(0).unwrap().start();
+        let line_start = text.get(..start).unwrap().rfind('\n').map_or(0, |i| i + 1);
+        let is_inline = !text.get(line_start..start).unwrap().trim().is_empty();
+        // flags without value are "true"; ex) "skip"
+        let mut annotations = BTreeMap::new();
+        for item in re4item.captures_iter(&cap["body"]) {
+            let value = item
+                .name("quoted")
+                .or(item.name("value"))
+                .map_or("true", |v| v.as_str());
+            annotations.insert(item["key"].to_string(), value.to_string());
+        }
+        if cap.name("file").is_some() {
+            file_level.extend(annotations);
+        } else {
+            result.push((lines.line_at(start), is_inline, annotations));
+        }
+    }
+    (file_level, result)
+}
+
+/// spans of the comments in C source code; string and char literals are skipped
+///
+fn get_comment_spans(code: &str) -> Vec<(usize, usize)> {
+    let bytes = code.as_bytes();
+    let mut result = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'/', Some(b'/')) => {
+                let end = code
+                    .get(i..)
+                    .unwrap()
+                    .find('\n')
+                    .map_or(code.len(), |n| i + n);
+                result.push((i, end));
+                i = end;
+            }
+            (b'/', Some(b'*')) => {
+                let end = code
+                    .get(i + 2..)
+                    .unwrap()
+                    .find("*/")
+                    .map_or(code.len(), |n| i + 2 + n + 2);
+                result.push((i, end));
+                i = end;
+            }
+            (b'"', _) | (b'\'', _) => {
+                let quote = bytes[i];
+                // encoding prefix of the literal such as "L", "u8" or "R", or the digits before a separator
+                let prefix_start = bytes[..i]
+                    .iter()
+                    .rposition(|b| !(b.is_ascii_alphanumeric() || *b == b'_' || *b == b'\''))
+                    .map_or(0, |p| p + 1);
+                let prefix = code.get(prefix_start..i).unwrap();
+                // a quote in a number is a digit separator such as "1'000"
+                if quote == b'\'' && prefix.starts_with(|c: char| c.is_ascii_digit()) {
+                    i += 1;
+                    continue;
+                }
+                // raw string literal such as R"x(...)x" may have quotes and line breaks
+                if quote == b'"' && matches!(prefix, "R" | "LR" | "uR" | "UR" | "u8R") {
+                    if let Some(open) = code.get(i + 1..).unwrap().find('(') {
+                        let close = format!("){}\"", code.get(i + 1..i + 1 + open).unwrap());
+                        i = code
+                            .get(i + 1 + open..)
+                            .unwrap()
+                            .find(&close)
+                            .map_or(code.len(), |n| i + 1 + open + n + close.len());
+                        continue;
+                    }
+                }
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote && bytes[i] != b'\n' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            _ => i += 1,
         }
     }
+    result
 }
 
-/// remove comments from C source code
+/// remove comments from C source code; line breaks within comments are kept, and string literals
+/// such as "http://" are not taken as comments
 ///
 fn remove_comments(code: &str) -> String {
-    let re = Regex::new(r"(/\*([^*]|[\r\n]|(\*+([^*/]|[\r\n])))*\*+/)|(//.*)").unwrap();
-    re.replace_all(&code, "").to_string()
+    remove_comments_with_offsets(code).0
+}
+
+/// remove comments from C source code with the mapping to the offsets of the given code
+///
+fn remove_comments_with_offsets(code: &str) -> (String, OffsetMap) {
+    let mut result = String::with_capacity(code.len());
+    let mut offsets = OffsetMap::default();
+    let mut last = 0;
+    for (start, end) in get_comment_spans(code) {
+        result.push_str(code.get(last..start).unwrap());
+        result.push_str(&"\n".repeat(code.get(start..end).unwrap().matches('\n').count()));
+        offsets.push(result.len(), end);
+        last = end;
+    }
+    result.push_str(code.get(last..).unwrap());
+    (result, offsets)
+}
+
+/// table of constants predefined by options, defined in C source code, and in the headers found in include paths
+///
+fn get_consts(code: &str, options: &ParserOptions) -> ConstTable {
+    let mut consts = ConstTable::new(options.data_model);
+    for op in &options.macros {
+        match op {
+            MacroOp::Define(name, value) => consts.define(name, value),
+            MacroOp::Undefine(name) => consts.undefine(name),
+        }
+    }
+    let mut visited = HashSet::new();
+    for path in &options.forced_includes {
+        if visited.insert(path.clone()) {
+            if let Ok(decoded) = read_text(path, options.encoding) {
+                let text = remove_comments(&decoded.text);
+                collect_included_consts(&text, options, &mut visited, &mut consts);
+                consts.collect(&text);
+            }
+        }
+    }
+    collect_included_consts(code, options, &mut visited, &mut consts);
+    consts.collect(code);
+    consts
+}
+
+/// collect constants from the included headers recursively
+///
+fn collect_included_consts(
+    code: &str,
+    options: &ParserOptions,
+    visited: &mut HashSet<PathBuf>,
+    consts: &mut ConstTable,
+) {
+    let re = Regex::new(r#"#include\s+["<](?<header>[^">]+)[">]"#).unwrap();
+    for cap in re.captures_iter(code) {
+        let header = cap.name("header").unwrap().as_str();
+        let found = options
+            .include_paths
+            .iter()
+            .map(|dir| dir.join(header))
+            .find(|path| path.is_file());
+        if let Some(path) = found {
+            if !visited.insert(path.clone()) {
+                continue;
+            }
+            if let Ok(decoded) = read_text(&path, options.encoding) {
+                let text = remove_comments(&decoded.text);
+                collect_included_consts(&text, options, visited, consts);
+                consts.collect(&text);
+            }
+        }
+    }
+}
+
+/// split array size expression and its evaluated value
+///
+fn get_array_size(cap: &regex::Captures, consts: &ConstTable) -> (String, Option<i32>) {
+    match cap.name("array_size") {
+        Some(c) => {
+            let expr = c.as_str().trim().to_string();
+            let size = consts.eval(&expr).and_then(|v| i32::try_from(v).ok());
+            (expr, size)
+        }
+        None => (String::new(), None),
+    }
 }
 
 /// list of inclusion from C source code
@@ -127,12 +1127,15 @@ fn remove_comments(code: &str) -> String {
 fn get_incs(code: &str) -> Vec<Include> {
     let mut result = vec![];
     let re = Regex::new(r#"(?P<captured>#include[\s]+["<].+[">])"#).unwrap();
+    let mut lines = LineCounter::new(code);
     for cap in re.captures_iter(code) {
         result.push(Include {
             captured: cap.name("captured").unwrap().as_str().trim().to_string(),
+            file: String::new(),
+            line: lines.line_at(cap.get(0).unwrap().start()),
         });
     }
-    result.dedup();
+    result.dedup_by(|a, b| a.captured == b.captured);
     result
 }
 
@@ -141,49 +1144,364 @@ fn get_incs(code: &str) -> Vec<Include> {
 fn get_typedefs(code: &str) -> Vec<Typedefs> {
     let mut result = vec![];
     let re = Regex::new(r#"(?P<captured>typedef\s+(?:.*?\{[.\s\S]*?\}.*?;|[.\s\S]+?;))"#).unwrap();
+    let mut lines = LineCounter::new(code);
     for cap in re.captures_iter(code) {
         result.push(Typedefs {
             captured: cap.name("captured").unwrap().as_str().trim().to_string(),
+            file: String::new(),
+            line: lines.line_at(cap.get(0).unwrap().start()),
         });
     }
-    result.dedup();
+    result.dedup_by(|a, b| a.captured == b.captured);
     result
 }
 
-/// update the list of static variables with LOCAL_STATIC_VARIABLE string pattern
+/// names defined by "typedef"; ex) "Direction_t" of "typedef enum { Idle } Direction_t;"
 ///
-fn update_static_vars_with_lsv(
-    code: &str,
-    fncs: &Vec<Function>,
-    lsv_macro_name: &str,
-    static_vars: &mut Vec<StaticVariable>,
-) {
-    let regex_str = format!(
-        "{}\\((?<fnc_name>\\w+)\\s*,(?<dtype>.*?)\\s*,\\s*(?<name>\\w+)\\s*(?:\\[(?<array_size>.*?)\\])?\\s*,\\s*(?<value>.*?)\\).*?;",
-        &lsv_macro_name
-    );
-    let re = Regex::new(&regex_str).unwrap();
+fn get_typedef_names(code: &str) -> HashSet<String> {
+    let re4fnptr = Regex::new(r"\(\s*\*\s*(?<name>\w+)\s*\)").unwrap();
+    let re4name = Regex::new(r"(?<name>\w+)\s*(?:\[[^\]]*\]\s*)*$").unwrap();
+    let mut result = HashSet::new();
+    for typedef in get_typedefs(code) {
+        let text = typedef.captured.trim_end_matches(';');
+        if let Some(cap) = re4fnptr.captures(text) {
+            result.insert(cap["name"].to_string());
+            continue;
+        }
+        // names follow the closing brace if any; ex) "typedef struct { int x; } Point_t, *PointPtr_t;"
+        let names = text.rsplit_once('}').map_or(text, |(_, names)| names);
+        for name in names.split(',') {
+            if let Some(cap) = re4name.captures(name) {
+                result.insert(cap["name"].to_string());
+            }
+        }
+    }
+    result
+}
+
+/// true if the identifier names a primitive type, a standard type such as "size_t" or a typedef
+///
+fn is_known_type(word: &str, type_names: &HashSet<String>) -> bool {
+    matches!(
+        word,
+        "void"
+            | "char"
+            | "short"
+            | "int"
+            | "long"
+            | "float"
+            | "double"
+            | "signed"
+            | "unsigned"
+            | "_Bool"
+            | "bool"
+    ) || word.ends_with("_t")
+        || type_names.contains(word)
+}
+
+/// list of tagged struct, union and enum definitions at file scope such as "struct point { int x; int y; };"
+///
+fn get_tagged_types(code: &str) -> Vec<Typedefs> {
+    let mut result = vec![];
+    let masked = mask_blocks(code);
+    let re = Regex::new(r"(?m)^[ \t]*(?:struct|union|enum)\s+\w+\s*\{").unwrap();
+    let mut lines = LineCounter::new(code);
+    for m in re.find_iter(&masked) {
+        let start = m.end();
+        let stop = find_end_of_func(code, start);
+        let rest = code.get(stop + 1..).unwrap_or_default();
+        if rest.trim_start().starts_with(';') {
+            let end = stop + 1 + rest.find(';').unwrap() + 1;
+            result.push(Typedefs {
+                captured: code.get(m.start()..end).unwrap().trim().to_string(),
+                file: String::new(),
+                line: lines.line_at(m.start()),
+            });
+        }
+    }
+    result
+}
+
+/// list of "#define" from C source code
+///
+fn get_macros(code: &str, consts: &ConstTable) -> Vec<Macro> {
+    let mut result = vec![];
+    let re = Regex::new(
+        r"(?m)^[ \t]*#[ \t]*define[ \t]+(?<name>\w+)(?:\((?<params>[^)]*)\))?(?<value>(?:[^\n\\]|\\\r?\n|\\)*)",
+    )
+    .unwrap();
+    let re4space = Regex::new(r"\s+").unwrap();
+    let mut lines = LineCounter::new(code);
     for cap in re.captures_iter(code) {
-        let captured = cap.get(0).unwrap().as_str().trim().to_string();
-        let dtype = cap.name("dtype").unwrap().as_str().trim().to_string();
-        let name = cap.name("name").unwrap().as_str().trim().to_string();
-        let array_size = cap
-            .name("array_size")
-            .map_or(0, |c| c.as_str().parse().unwrap_or(0));
-        let init = cap
-            .name("value")
-            .map_or("0", |c| c.as_str().trim())
+        let value = cap.name("value").unwrap().as_str();
+        let value = re4space
+            .replace_all(&value.replace("\\\n", " ").replace("\\\r\n", " "), " ")
+            .trim()
             .to_string();
-        let is_const = cap
-            .name("dtype")
-            .map_or(false, |c| c.as_str().to_lowercase().contains("const"));
-        let name_expr = cap.name("array_size").map_or(name.clone(), |c| {
-            (name.clone() + "[" + c.as_str().trim() + "]").to_string()
+        let params = cap.name("params").map_or(vec![], |c| {
+            c.as_str()
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect()
         });
-        let mut is_local = false;
-        let mut func_name = String::from("");
-        for func in fncs {
-            if let Some(pos) = code.find(func.captured.as_str()) {
+        let is_function_like = cap.name("params").is_some();
+        result.push(Macro {
+            captured: cap.get(0).unwrap().as_str().trim().to_string(),
+            file: String::new(),
+            line: lines.line_at(cap.get(0).unwrap().start()),
+            name: cap.name("name").unwrap().as_str().to_string(),
+            is_function_like,
+            params,
+            int_value: if is_function_like {
+                None
+            } else {
+                consts.eval(&value)
+            },
+            value,
+            is_public: false,
+        });
+    }
+    result
+}
+
+/// list of extern variables at file scope
+///
+fn get_extern_vars(code: &str, consts: &ConstTable) -> Vec<ExternVariable> {
+    let mut result = vec![];
+    let masked = mask_blocks(code);
+    let re = Regex::new(r"(?m)^[ \t]*extern\s+(?<dtype>[\w \t\*]+?)\s*\b(?<name>\w+)\s*(?:\[(?<array_size>[^\]]*)\])?\s*;").unwrap();
+    let mut lines = LineCounter::new(code);
+    for cap in re.captures_iter(&masked) {
+        let captured = cap.get(0).unwrap().as_str().trim().to_string();
+        let dtype = cap.name("dtype").unwrap().as_str().trim().to_string();
+        let name = cap.name("name").unwrap().as_str().to_string();
+        let (array_size_expr, array_size) = get_array_size(&cap, consts);
+        let name_expr = cap.name("array_size").map_or(name.clone(), |c| {
+            name.clone() + "[" + c.as_str().trim() + "]"
+        });
+        result.push(ExternVariable {
+            captured,
+            file: String::new(),
+            line: lines.line_at(cap.get(0).unwrap().start()),
+            name_expr,
+            name,
+            is_const: dtype.split_whitespace().any(|w| w == "const"),
+            dtype,
+            array_size_expr,
+            array_size,
+            is_public: false,
+            annotations: BTreeMap::new(),
+        });
+    }
+    result
+}
+
+/// list of non-static variables defined at file scope
+///
+fn get_global_vars(code: &str, consts: &ConstTable) -> Vec<GlobalVariable> {
+    let mut result = vec![];
+    let masked = mask_blocks(&mask_literals(code));
+    let re = Regex::new(r"(?m)^[ \t]*(?<dtype>\w[\w \t\*]*?)\s*\b(?<name>\w+)\s*(?:\[(?<array_size>[^\]]*)\])?\s*(?:=\s*(?<value>\{[^;]*?\}|[^;\n]*?))?\s*;").unwrap();
+    let mut lines = LineCounter::new(code);
+    for cap in re.captures_iter(&masked) {
+        let dtype = cap.name("dtype").unwrap().as_str().trim().to_string();
+        // skip declarations which are not definitions such as "extern int a;" or "struct tag;"
+        if dtype
+            .split_whitespace()
+            .any(|w| matches!(w, "static" | "STATIC" | "extern" | "typedef" | "return"))
+            || matches!(dtype.as_str(), "struct" | "union" | "enum")
+        {
+            continue;
+        }
+        let start = cap.get(0).unwrap().start();
+        let name = cap.name("name").unwrap().as_str().to_string();
+        let (array_size_expr, array_size) = get_array_size(&cap, consts);
+        let name_expr = cap.name("array_size").map_or(name.clone(), |c| {
+            name.clone() + "[" + c.as_str().trim() + "]"
+        });
+        // init value is taken from the code since the masked one has no contents of braces
+        let init = cap.name("value").map_or(String::new(), |c| {
+            code.get(c.start()..c.end()).unwrap().trim().to_string()
+        });
+        result.push(GlobalVariable {
+            captured: code
+                .get(start..cap.get(0).unwrap().end())
+                .unwrap()
+                .trim()
+                .to_string(),
+            file: String::new(),
+            line: lines.line_at(start),
+            name_expr,
+            name,
+            is_const: dtype.split_whitespace().any(|w| w == "const"),
+            dtype,
+            init,
+            array_size_expr,
+            array_size,
+            section: String::new(),
+            annotations: BTreeMap::new(),
+        });
+    }
+    result
+}
+
+/// replace braces and semicolons in string and char literals with spaces, so initializers such as
+/// `{"a;b"}` are matched as a whole; the offsets are kept to take the original text from the code
+///
+fn mask_literals(code: &str) -> String {
+    let mut result = String::with_capacity(code.len());
+    let mut quote = None;
+    let mut escaped = false;
+    for c in code.chars() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q || c == '\n' {
+                quote = None;
+            }
+            if quote.is_some() && matches!(c, ';' | '{' | '}') {
+                result.push(' ');
+                continue;
+            }
+        } else if c == '"' || c == '\'' {
+            quote = Some(c);
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// replace the contents of blocks at file scope with spaces to keep only file scope declarations;
+/// blocks of `extern "C"` and namespaces are kept as they are
+///
+fn mask_blocks(code: &str) -> String {
+    let mut result = String::with_capacity(code.len());
+    let mut stack: Vec<bool> = vec![];
+    for (i, c) in code.char_indices() {
+        let is_masked = stack.iter().any(|transparent| !transparent);
+        match c {
+            '{' => {
+                let is_transparent = !is_masked
+                    && matches!(
+                        get_block_kind(block_head(code, i)).0,
+                        BlockKind::ExternC | BlockKind::Namespace
+                    );
+                stack.push(is_transparent);
+            }
+            '}' => {
+                stack.pop();
+            }
+            _ => {}
+        }
+        let is_masked = is_masked && stack.iter().any(|transparent| !transparent);
+        if is_masked && c != '\n' {
+            result.extend(std::iter::repeat_n(' ', c.len_utf8()));
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// update the list of static variables with the local static variable macro; ex) LOCAL_STATIC_VARIABLE
+///
+fn update_static_vars_with_lsv(
+    code: &str,
+    fncs: &Vec<Function>,
+    lsv_macro: &LsvMacro,
+    consts: &ConstTable,
+    static_vars: &mut Vec<StaticVariable>,
+) {
+    let re = lsv_macro.regex();
+    let mut lines = LineCounter::new(code);
+    for cap in re.captures_iter(code) {
+        let captured = cap.get(0).unwrap().as_str().trim().to_string();
+        let line = lines.line_at(cap.get(0).unwrap().start());
+        let dtype = cap.name("dtype").unwrap().as_str().trim().to_string();
+        let name = cap.name("name").unwrap().as_str().trim().to_string();
+        let (array_size_expr, array_size) = get_array_size(&cap, consts);
+        let init = cap
+            .name("value")
+            .map_or("0", |c| c.as_str().trim())
+            .to_string();
+        let initializer = Initializer::parse(&init, consts);
+        let is_const = cap
+            .name("dtype")
+            .is_some_and(|c| c.as_str().to_lowercase().contains("const"));
+        let name_expr = cap.name("array_size").map_or(name.clone(), |c| {
+            (name.clone() + "[" + c.as_str().trim() + "]").to_string()
+        });
+        let mut is_local = false;
+        let mut func_name = String::from("");
+        for func in fncs {
+            if let Some(pos) = code.find(func.captured.as_str()) {
+                let start = pos + code.get(pos..).unwrap().find('{').unwrap() + 1;
+                let stop = find_end_of_func(code, start);
+                let body = code.get(start..stop).unwrap();
+                if body.contains(captured.as_str()) {
+                    is_local = true;
+                    func_name = func.name.to_string();
+                }
+            }
+        }
+        // the function name given as argument is used outside of function bodies
+        if let (false, Some(fnc_name)) = (is_local, cap.name("fnc_name")) {
+            is_local = true;
+            func_name = fnc_name.as_str().to_string();
+        }
+        static_vars.push(StaticVariable {
+            captured,
+            file: String::new(),
+            line,
+            name_expr,
+            name,
+            dtype,
+            is_local,
+            func_name,
+            init,
+            initializer,
+            array_size_expr,
+            array_size,
+            is_const,
+            lsv_macro: lsv_macro.name.clone(),
+            section: String::new(),
+            annotations: BTreeMap::new(),
+        });
+    }
+}
+
+/// list of static variables from C source code
+///
+fn get_static_vars(code: &str, fncs: &Vec<Function>, consts: &ConstTable) -> Vec<StaticVariable> {
+    let mut result = vec![];
+    let re = Regex::new(r"(?i)(?<keyword>static\s+|static\s+const\s+|const\s+static\s+)+(?<dtype>.*?)(?<name>\w+)\s*(?:\[(?<array_size>.*?)\])?\s*(?:=\s*(?<value>\{[^;]*?\}|.*?))?;").unwrap();
+    let mut lines = LineCounter::new(code);
+    // literals are masked to match initializers such as `{"a;b"}`, and the text is taken from the code
+    let masked = mask_literals(code);
+    for cap in re.captures_iter(&masked) {
+        let captured = code[cap.get(0).unwrap().range()].trim().to_string();
+        let line = lines.line_at(cap.get(0).unwrap().start());
+        let dtype = cap.name("dtype").unwrap().as_str().trim().to_string();
+        let name = cap.name("name").unwrap().as_str().trim().to_string();
+        let (array_size_expr, array_size) = get_array_size(&cap, consts);
+        let init = cap
+            .name("value")
+            .map_or("0", |c| code[c.range()].trim())
+            .to_string();
+        let initializer = Initializer::parse(&init, consts);
+        let is_const = cap
+            .name("keyword")
+            .is_some_and(|c| c.as_str().to_lowercase().contains("const"));
+        let name_expr = cap.name("array_size").map_or(name.clone(), |c| {
+            (name.clone() + "[" + c.as_str().trim() + "]").to_string()
+        });
+        let mut is_local = false;
+        let mut func_name = String::from("");
+        for func in fncs {
+            if let Some(pos) = code.find(func.captured.as_str()) {
                 let start = pos + code.get(pos..).unwrap().find('{').unwrap() + 1;
                 let stop = find_end_of_func(code, start);
                 let body = code.get(start..stop).unwrap();
@@ -193,160 +1511,1132 @@ fn update_static_vars_with_lsv(
                 }
             }
         }
-        static_vars.push(StaticVariable {
-            captured: captured,
-            name_expr: name_expr,
-            name: name,
-            dtype: dtype,
-            is_local: is_local,
-            func_name: func_name,
-            init: init,
-            array_size: array_size,
-            is_const: is_const,
-        });
+        result.push(StaticVariable {
+            captured,
+            file: String::new(),
+            line,
+            name_expr,
+            name,
+            dtype,
+            is_local,
+            func_name,
+            init,
+            initializer,
+            array_size_expr,
+            array_size,
+            is_const,
+            lsv_macro: String::new(),
+            section: String::new(),
+            annotations: BTreeMap::new(),
+        });
+    }
+    result
+}
+
+/// list of functions from C source code
+///
+fn get_fncs(code: &str) -> Vec<Function> {
+    let re = Regex::new(
+        r"((?<return>\w+(?:[\w\s\*]|__attribute__\s*\(\((?:[^()]|\([^()]*\))*\)\))*\s+)|FUNC\((?<return_ex>[^,]+),[^\)]+\)\s*)(?<name>\w+)[\w]*\s*\((?<args>[^=!><>;\(\)-]*)\)\s*\{"
+    ).unwrap();
+    get_fncs_by_regex(&mask_directives(code), &re)
+}
+
+/// replace preprocessor directives with spaces not to be a part of the following declaration;
+/// ex) "#pragma vector=TIMER0_A0_VECTOR" followed by a function
+///
+fn mask_directives(code: &str) -> String {
+    let re = Regex::new(r"(?m)^[ \t]*#(?:[^\n]*\\\n)*[^\n]*").unwrap();
+    re.replace_all(code, |cap: &regex::Captures| {
+        cap[0]
+            .chars()
+            .map(|c| {
+                if c == '\n' {
+                    "\n".to_string()
+                } else {
+                    " ".repeat(c.len_utf8())
+                }
+            })
+            .collect::<String>()
+    })
+    .to_string()
+}
+
+/// list of function prototypes at file scope from C source code
+///
+fn get_protos(code: &str) -> Vec<Function> {
+    let masked = mask_blocks(code);
+    let re = Regex::new(
+        r"(?m)^[ \t]*((?<return>\w[\w \t\*]*[\s\*])|FUNC\((?<return_ex>[^,]+),[^\)]+\)\s*)(?<name>\w+)\s*\((?<args>[^=!><>;\{\}\(\)-]*)\)\s*;"
+    ).unwrap();
+    get_fncs_by_regex(&masked, &re)
+        .into_iter()
+        .filter(|f| {
+            !f.rtype
+                .split_whitespace()
+                .any(|w| w == "typedef" || w == "return")
+        })
+        .collect()
+}
+
+/// list of functions captured by the given regex with named groups; return or return_ex, name, and args
+///
+fn get_fncs_by_regex(code: &str, re: &Regex) -> Vec<Function> {
+    get_fncs_with_offsets(code, re)
+        .into_iter()
+        .map(|(_, fnc)| fnc)
+        .collect()
+}
+
+/// list of functions captured by the given regex with their start offsets in the code
+///
+fn get_fncs_with_offsets(code: &str, re: &Regex) -> Vec<(usize, Function)> {
+    let mut result = vec![];
+    let re4sep = Regex::new(r"^(?<atype>.*?)(?<aname>\w+(?:\[.*?\])*)$").unwrap();
+    let re4const = Regex::new(r"\w[\s\r\n]+const[\s\r\n]*\*").unwrap();
+    let re4space = Regex::new(r"\s+").unwrap();
+    let re4bracket = Regex::new(r"(\[.*?\])+").unwrap();
+    let re4extern = Regex::new(r"\bextern\s+").unwrap();
+    let re4isr = Regex::new(r"\b(?:__interrupt|_interrupt|interrupt|__irq|__fiq|__isr)\b(?:\s*\([^()]*\))?|__attribute__\s*\(\((?<attr>(?:[^()]|\([^()]*\))*)\)\)").unwrap();
+    let get_atypes = |args: String| -> (String, String) {
+        let mut type_list = String::new();
+        let mut name_list = String::new();
+        let mut first_pos = true;
+        let arg_list = split_args(&args);
+        for arg in arg_list {
+            let arg = arg.trim();
+            let mut atype;
+            let mut aname;
+            if let Some(cap) = re4sep.captures(arg) {
+                atype = cap.name("atype").unwrap().as_str().trim().to_string();
+                aname = cap.name("aname").unwrap().as_str().trim().to_string();
+                // relocate 'const' only for 'datatype const *' -> 'const datatype *'
+                if re4const.captures(&atype).is_some() {
+                    atype = atype.replace("const", "");
+                    atype = format!("const {}", atype);
+                    atype = re4space.replace_all(&atype, " ").to_string();
+                }
+                if first_pos {
+                    first_pos = false;
+                } else {
+                    type_list.push_str(", ");
+                    name_list.push_str(", ");
+                }
+                type_list.push_str(&atype);
+                let array_dimension = aname.matches("[").count();
+                type_list.push_str(&"*".repeat(array_dimension));
+                // remove '[]' from name string
+                if re4bracket.captures(&aname).is_some() {
+                    aname = re4bracket.replace_all(&aname, "").to_string();
+                }
+                name_list.push_str(&aname);
+            }
+        }
+        if type_list.trim() == "void" {
+            type_list.clear();
+            name_list.clear();
+        }
+        (type_list, name_list)
+    };
+    let mut lines = LineCounter::new(code);
+    for cap in re.captures_iter(code) {
+        if cap.name("name").unwrap().as_str().trim() == "if" {
+            continue;
+        }
+        let mut raw_args = re4space
+            .replace_all(cap.name("args").unwrap().as_str().trim(), " ")
+            .replace("\\", "")
+            .trim()
+            .to_string();
+        if raw_args.trim() == "void" {
+            raw_args.clear();
+        }
+        let (atypes, anames) = get_atypes(raw_args.clone());
+        let rtype = cap
+            .name("return")
+            .or(cap.name("return_ex"))
+            .unwrap()
+            .as_str()
+            .replace("static", "")
+            .replace("STATIC", "")
+            .replace("inline", "")
+            .replace("INLINE", "");
+        let rtype = re4extern.replace_all(&rtype, "").to_string();
+        // interrupt keywords and attributes are not a part of the return type
+        let is_isr = re4isr.captures_iter(&rtype).any(|c| {
+            c.name("attr").is_none_or(|attr| {
+                let attr = attr.as_str().to_ascii_lowercase();
+                ["interrupt", "signal", "isr", "irq"]
+                    .iter()
+                    .any(|k| attr.contains(k))
+            })
+        });
+        let rtype = if re4isr.is_match(&rtype) {
+            let rtype = re4isr.replace_all(&rtype, "");
+            re4space.replace_all(rtype.trim(), " ").to_string()
+        } else {
+            rtype.trim().to_string()
+        };
+        let captured = cap.get(0).unwrap().as_str().trim().to_string();
+        let name = cap.name("name").unwrap().as_str().trim().to_string();
+        let start = cap.get(0).unwrap().start();
+        result.push((
+            start,
+            Function {
+                qualified_name: name.clone(),
+                name,
+                file: String::new(),
+                line: lines.line_at(start),
+                is_local: captured.to_ascii_lowercase().contains("static"),
+                is_inline: captured.to_ascii_lowercase().contains("inline"),
+                is_public: false,
+                captured,
+                rtype,
+                args: raw_args.clone(),
+                atypes,
+                anames,
+                namespace: String::new(),
+                class: String::new(),
+                is_isr,
+                section: String::new(),
+                body: String::new(),
+                body_span: None,
+                locals: vec![],
+                returns: vec![],
+                may_fall_through: false,
+                annotations: BTreeMap::new(),
+            },
+        ));
+    }
+    result
+}
+
+/// split arguments by commas which are not enclosed in parentheses, braces or angle brackets
+///
+fn split_args(args: &str) -> Vec<&str> {
+    let mut result = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' | '<' | '{' => depth += 1,
+            ')' | '>' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(&args[start..]);
+    result
+}
+
+/// kind of a block enclosed by braces
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BlockKind {
+    Namespace,
+    Class,
+    ExternC,
+    /// function body, initializer, or any other block
+    Other,
+}
+
+/// block enclosed by braces with the offsets of its braces
+struct Block {
+    kind: BlockKind,
+    name: String,
+    start: usize,
+    end: usize,
+}
+
+/// text preceding the brace at the given offset back to the end of the previous statement or block
+///
+fn block_head(code: &str, brace: usize) -> &str {
+    code.get(..brace)
+        .unwrap()
+        .rsplit([';', '{', '}'])
+        .next()
+        .unwrap_or("")
+        .trim()
+}
+
+/// kind and name of a block by its head; ex) "namespace drv" or "class Motor : public Base"
+///
+fn get_block_kind(head: &str) -> (BlockKind, String) {
+    // compiled once since it is called for every opening brace
+    static RE4NAMESPACE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"\bnamespace(?:\s+(?<name>\w+(?:\s*::\s*\w+)*))?\s*$").unwrap()
+    });
+    static RE4CLASS: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r"\b(?:class|struct|union)\s+(?:\w+\s+)*?(?<name>\w+)\s*(?:final\s*)?(?::[^(){}]*)?$",
+        )
+        .unwrap()
+    });
+    let re4namespace = &*RE4NAMESPACE;
+    let re4class = &*RE4CLASS;
+    if let Some(cap) = re4namespace.captures(head) {
+        let name = cap.name("name").map_or("", |c| c.as_str());
+        (BlockKind::Namespace, name.replace(char::is_whitespace, ""))
+    } else if head.trim_end().ends_with("extern \"C\"") {
+        (BlockKind::ExternC, String::new())
+    } else if let Some(cap) = re4class
+        .captures(head)
+        .filter(|_| !head.contains('(') && !head.split_whitespace().any(|w| w == "enum"))
+    {
+        (BlockKind::Class, cap["name"].to_string())
+    } else {
+        (BlockKind::Other, String::new())
+    }
+}
+
+/// list of all the blocks in C++ source code
+///
+fn get_blocks(code: &str) -> Vec<Block> {
+    let mut result = vec![];
+    let mut stack: Vec<Block> = vec![];
+    for (i, c) in code.char_indices() {
+        match c {
+            '{' => {
+                let (kind, name) = get_block_kind(block_head(code, i));
+                stack.push(Block {
+                    kind,
+                    name,
+                    start: i,
+                    end: code.len(),
+                });
+            }
+            '}' => {
+                if let Some(mut block) = stack.pop() {
+                    block.end = i;
+                    result.push(block);
+                }
+            }
+            _ => {}
+        }
+    }
+    result.extend(stack);
+    result.sort_by_key(|block| block.start);
+    result
+}
+
+/// list of functions from C++ source code; free functions, member functions defined in or out of
+/// classes, constructors, destructors, operators and templates
+///
+fn get_cpp_fncs(code: &str) -> Vec<Function> {
+    let re = Regex::new(
+        r"(?m)^[ \t]*(?:template\s*<[^;{}]*?>\s*)?(?<return>(?:[\w~:][\w:<>,]*[\s\*&]+)*?)(?<name>(?:\w+(?:<[^<>;{}()]*>)?\s*::\s*)*(?:~?\w+|operator\s*(?:\(\)|[^\s\w(]+)))\s*\((?<args>[^;{}]*?)\)(?:\s*(?:const|volatile|noexcept|override|final|&&|&))*\s*(?::[^;{}]*)?\{"
+    )
+    .unwrap();
+    let re4template = Regex::new(r"<[^<>]*>").unwrap();
+    let blocks = get_blocks(code);
+    let mut result = vec![];
+    for (start, mut fnc) in get_fncs_with_offsets(&mask_directives(code), &re) {
+        let enclosing: Vec<&Block> = blocks
+            .iter()
+            .filter(|b| b.start < start && start < b.end)
+            .collect();
+        // functions are defined only at namespace or class scope
+        if enclosing.iter().any(|b| b.kind == BlockKind::Other) {
+            continue;
+        }
+        let mut namespaces: Vec<String> = enclosing
+            .iter()
+            .filter(|b| b.kind == BlockKind::Namespace && !b.name.is_empty())
+            .map(|b| b.name.clone())
+            .collect();
+        let mut classes: Vec<String> = enclosing
+            .iter()
+            .filter(|b| b.kind == BlockKind::Class)
+            .map(|b| b.name.clone())
+            .collect();
+        // qualifiers of an out-of-class definition such as "Motor::setDir"
+        let mut qualifiers: Vec<String> = fnc
+            .name
+            .split("::")
+            .map(|q| re4template.replace_all(q, "").trim().to_string())
+            .collect();
+        fnc.name = qualifiers.pop().unwrap_or_default();
+        for qualifier in qualifiers {
+            let is_namespace = blocks
+                .iter()
+                .any(|b| b.kind == BlockKind::Namespace && b.name == qualifier);
+            if is_namespace && classes.is_empty() {
+                namespaces.push(qualifier);
+            } else {
+                classes.push(qualifier);
+            }
+        }
+        let owner = classes.last().map_or("", |c| c.as_str());
+        let is_special = fnc.name.starts_with('~')
+            || fnc.name.starts_with("operator")
+            || (!owner.is_empty() && fnc.name == owner);
+        // calls or macros such as "TEST_F(Fixture, name) {" have no return type
+        if is_c_keyword(&fnc.name) || (fnc.rtype.is_empty() && !is_special) {
+            continue;
+        }
+        fnc.rtype = fnc
+            .rtype
+            .split_whitespace()
+            .filter(|w| {
+                !matches!(
+                    *w,
+                    "public:"
+                        | "protected:"
+                        | "private:"
+                        | "virtual"
+                        | "explicit"
+                        | "constexpr"
+                        | "friend"
+                )
+            })
+            .collect::<Vec<&str>>()
+            .join(" ");
+        // members have external linkage, while functions in unnamed namespaces are local
+        let is_unnamed = enclosing
+            .iter()
+            .any(|b| b.kind == BlockKind::Namespace && b.name.is_empty());
+        fnc.is_local = (fnc.is_local && classes.is_empty()) || is_unnamed;
+        fnc.namespace = namespaces.join("::");
+        fnc.class = classes.join("::");
+        fnc.qualified_name = namespaces
+            .iter()
+            .chain(classes.iter())
+            .chain(std::iter::once(&fnc.name))
+            .cloned()
+            .collect::<Vec<String>>()
+            .join("::");
+        result.push(fnc);
+    }
+    result
+}
+
+/// list of interrupt handlers defined by ISR macro; ex) "ISR(TIMER0_vect) {"
+///
+fn get_isr_macro_fncs(code: &str) -> Vec<Function> {
+    let re =
+        Regex::new(r"(?m)^[ \t]*(?<return>)ISR\s*\(\s*(?<name>\w+)\s*(?:,[^()]*)?\)(?<args>)\s*\{")
+            .unwrap();
+    get_fncs_by_regex(code, &re)
+        .into_iter()
+        .map(|mut fnc| {
+            fnc.rtype = "void".to_string();
+            fnc.is_isr = true;
+            fnc
+        })
+        .collect()
+}
+
+/// list of "#pragma" from C source code
+///
+fn get_pragmas(code: &str, bodies: &[FuncBody]) -> Vec<Pragma> {
+    let mut result = vec![];
+    let re = Regex::new(r"(?m)^[ \t]*#[ \t]*pragma[ \t]+(?<text>.*?)[ \t]*$").unwrap();
+    let mut lines = LineCounter::new(code);
+    for cap in re.captures_iter(code) {
+        let start = cap.get(0).unwrap().start();
+        result.push(Pragma {
+            captured: cap.get(0).unwrap().as_str().trim().to_string(),
+            file: String::new(),
+            line: lines.line_at(start),
+            text: cap["text"].to_string(),
+            func_name: func_name_at(bodies, start),
+        });
+    }
+    result
+}
+
+/// mark interrupt handlers declared by pragmas at file scope; the function named in the pragma
+/// such as "#pragma interrupt(tick)", otherwise the next function such as "#pragma vector=TIMER0_A0_VECTOR"
+///
+fn mark_isrs(fncs: &mut [Function], pragmas: &[Pragma]) {
+    let re = Regex::new(r"(?i)^(?:vector|interrupt\w*|isr)\b").unwrap();
+    for pragma in pragmas {
+        if !pragma.func_name.is_empty() || !re.is_match(&pragma.text) {
+            continue;
+        }
+        let words: Vec<&str> = pragma
+            .text
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .skip(1)
+            .collect();
+        let index = fncs
+            .iter()
+            .position(|f| words.contains(&f.name.as_str()))
+            .or_else(|| fncs.iter().position(|f| f.line > pragma.line));
+        if let Some(index) = index {
+            fncs[index].is_isr = true;
+        }
+    }
+}
+
+/// list of inline assembly from C source code; ex) "__asm__ volatile (\"nop\");" or "__asm { nop }"
+///
+fn get_asm_blocks(code: &str, bodies: &[FuncBody]) -> Vec<AsmBlock> {
+    let mut result = vec![];
+    let re = Regex::new(
+        r"\b(?:__asm__|__asm|asm)\b(?:\s+(?:volatile|__volatile__|goto|inline))*\s*(?<open>[\(\{])",
+    )
+    .unwrap();
+    let mut lines = LineCounter::new(code);
+    for cap in re.captures_iter(code) {
+        let start = cap.get(0).unwrap().start();
+        let open = cap.name("open").unwrap();
+        let close_char = if open.as_str() == "(" { ')' } else { '}' };
+        // find the closing bracket skipping string literals
+        let mut depth = 0;
+        let mut quote = None;
+        let mut escaped = false;
+        let mut close = None;
+        for (i, c) in code.get(open.start()..).unwrap().char_indices() {
+            if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            } else if c == '"' || c == '\'' {
+                quote = Some(c);
+            } else if c == open.as_str().chars().next().unwrap() {
+                depth += 1;
+            } else if c == close_char {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(open.start() + i);
+                    break;
+                }
+            }
+        }
+        let Some(close) = close else {
+            continue;
+        };
+        let mut end = close + 1;
+        if code.get(end..).unwrap().trim_start().starts_with(';') {
+            end += code.get(end..).unwrap().find(';').unwrap() + 1;
+        }
+        result.push(AsmBlock {
+            captured: code.get(start..end).unwrap().to_string(),
+            file: String::new(),
+            line: lines.line_at(start),
+            code: code.get(open.end()..close).unwrap().trim().to_string(),
+            func_name: func_name_at(bodies, start),
+        });
+    }
+    result
+}
+
+/// matches of user defined extractors by name; extractors of function scope search each function body,
+/// and the span is given in the original text if any
+///
+fn get_extractions(
+    code: &str,
+    original: Option<(&str, &OffsetMap)>,
+    bodies: &[FuncBody],
+    extractors: &[Extractor],
+) -> BTreeMap<String, Vec<Extraction>> {
+    let mut result = BTreeMap::new();
+    let mut lines = LineCounter::new(code);
+    for extractor in extractors {
+        // invalid patterns are reported when the config is loaded
+        let Ok(re) = extractor.regex() else {
+            continue;
+        };
+        let ranges: Vec<(usize, usize, String)> = match extractor.scope {
+            ExtractorScope::File => vec![(0, code.len(), String::new())],
+            ExtractorScope::Function => bodies
+                .iter()
+                .map(|b| (b.start, b.stop, b.name.clone()))
+                .collect(),
+        };
+        let list: &mut Vec<Extraction> = result.entry(extractor.name.clone()).or_default();
+        for (start, stop, func_name) in ranges {
+            for cap in re.captures_iter(code.get(start..stop).unwrap()) {
+                let m = cap.get(0).unwrap();
+                let (begin, end) = (start + m.start(), start + m.end());
+                let mut groups = BTreeMap::new();
+                for name in re.capture_names().flatten() {
+                    if let Some(group) = cap.name(name) {
+                        groups.insert(name.to_string(), group.as_str().to_string());
+                    }
+                }
+                let span = match original {
+                    Some((_, offsets)) => (offsets.original(begin), offsets.original(end)),
+                    None => (begin, end),
+                };
+                let line = lines.line_at(begin);
+                list.push(Extraction {
+                    captured: m.as_str().to_string(),
+                    file: String::new(),
+                    line,
+                    span: Span {
+                        start: span.0,
+                        end: span.1,
+                        start_line: line,
+                        end_line: line + m.as_str().trim_end_matches('\n').matches('\n').count(),
+                    },
+                    func_name: if func_name.is_empty() {
+                        func_name_at(bodies, begin)
+                    } else {
+                        func_name.clone()
+                    },
+                    groups,
+                });
+            }
+        }
+    }
+    result
+}
+
+/// changes of memory section by line; AUTOSAR "*_START_SEC_*" and "*_STOP_SEC_*" defines,
+/// and "#pragma section"; an empty name is the default section
+///
+fn get_sections(code: &str) -> Vec<(usize, String)> {
+    let re = Regex::new(r#"(?m)^[ \t]*#[ \t]*(?:define[ \t]+(?:\w*?_)?(?<memmap>START|STOP)_SEC_(?<name>\w+)|pragma[ \t]+section\b(?<pragma>.*))$"#).unwrap();
+    let re4quoted = Regex::new(r#""(?<name>[^"]*)""#).unwrap();
+    let mut result = vec![];
+    let mut lines = LineCounter::new(code);
+    for cap in re.captures_iter(code) {
+        let line = lines.line_at(cap.get(0).unwrap().start());
+        let name = if let Some(memmap) = cap.name("memmap") {
+            match memmap.as_str() {
+                "START" => cap["name"].to_string(),
+                _ => String::new(),
+            }
+        } else {
+            // the last quoted name if any; ex) "#pragma section code \".fast\""
+            let pragma = cap["pragma"].trim();
+            re4quoted
+                .captures_iter(pragma)
+                .last()
+                .map_or(pragma.to_string(), |c| c["name"].to_string())
+        };
+        result.push((line, name));
+    }
+    result
+}
+
+/// memory section active at the given line
+///
+fn section_at(sections: &[(usize, String)], line: usize) -> String {
+    sections
+        .iter()
+        .rfind(|(l, _)| *l < line)
+        .map_or(String::new(), |(_, name)| name.clone())
+}
+
+/// range of the function body within its braces
+///
+fn find_body(code: &str, fnc: &Function) -> Option<(usize, usize)> {
+    let pos = code.find(fnc.captured.as_str())?;
+    let start = pos + code.get(pos..)?.find('{')? + 1;
+    Some((start, find_end_of_func(code, start)))
+}
+
+/// range of a function body within its braces
+#[derive(Debug, Clone)]
+struct FuncBody {
+    start: usize,
+    stop: usize,
+    name: String,
+}
+
+/// bodies of the functions sorted by offset
+///
+fn get_bodies(code: &str, fncs: &[Function]) -> Vec<FuncBody> {
+    let mut bodies: Vec<FuncBody> = fncs
+        .iter()
+        .filter_map(|f| {
+            find_body(code, f).map(|(start, stop)| FuncBody {
+                start,
+                stop,
+                name: f.name.clone(),
+            })
+        })
+        .collect();
+    bodies.sort_by_key(|b| b.start);
+    bodies
+}
+
+/// name of the function whose body contains the given offset; empty at file scope
+///
+fn func_name_at(bodies: &[FuncBody], offset: usize) -> String {
+    // bodies do not overlap, so only the last one starting before the offset can contain it
+    let index = bodies.partition_point(|b| b.start <= offset);
+    bodies[..index]
+        .last()
+        .filter(|b| offset < b.stop)
+        .map_or(String::new(), |b| b.name.clone())
+}
+
+/// update functions with their bodies and local variables; the body is taken from the original text
+/// with comments if given, otherwise from the code
+///
+fn update_fncs_with_body(
+    code: &str,
+    original: Option<(&str, &OffsetMap)>,
+    consts: &ConstTable,
+    fncs: &mut [Function],
+) {
+    let mut lines = LineCounter::new(code);
+    let type_names = get_typedef_names(code);
+    for fnc in fncs.iter_mut() {
+        let Some((start, stop)) = find_body(code, fnc) else {
+            continue;
+        };
+        // include the braces
+        let (start, end) = (start - 1, (stop + 1).min(code.len()));
+        let (body, span) = match original {
+            Some((text, offsets)) => {
+                let (start, end) = (offsets.original(start), offsets.original(end));
+                (text.get(start..end).unwrap_or_default(), (start, end))
+            }
+            None => (code.get(start..end).unwrap(), (start, end)),
+        };
+        fnc.body = body.to_string();
+        fnc.body_span = Some(Span {
+            start: span.0,
+            end: span.1,
+            start_line: lines.line_at(start),
+            end_line: lines.line_at(end - 1),
+        });
+        fnc.locals = get_locals(code, start + 1, end - 1, consts, &type_names);
+        fnc.returns = get_returns(code, start + 1, end - 1, consts);
+        let is_void = fnc.rtype.is_empty()
+            || fnc
+                .rtype
+                .split_whitespace()
+                .all(|w| matches!(w, "void" | "const" | "volatile"));
+        fnc.may_fall_through = !is_void && !ends_with_return(code.get(start + 1..end - 1).unwrap());
+    }
+}
+
+/// list of return statements in the given range of a function body
+///
+fn get_returns(code: &str, start: usize, end: usize, consts: &ConstTable) -> Vec<ReturnInfo> {
+    // compiled once since it is called for every function
+    static RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\breturn\b\s*(?<expr>[^;]*);").unwrap());
+    let re = &*RE;
+    let mut result = vec![];
+    let mut lines = LineCounter::new(code);
+    let body = code.get(start..end).unwrap();
+    for cap in re.captures_iter(body) {
+        let expr = collapse_spaces(&cap["expr"]);
+        let (kind, value, callee) = classify_return(&expr, consts);
+        result.push(ReturnInfo {
+            captured: cap.get(0).unwrap().as_str().to_string(),
+            file: String::new(),
+            line: lines.line_at(start + cap.get(0).unwrap().start()),
+            expr,
+            kind,
+            value,
+            callee,
+        });
+    }
+    result
+}
+
+/// trim and collapse whitespaces
+///
+fn collapse_spaces(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// kind, value and callee of a returned expression
+///
+fn classify_return(expr: &str, consts: &ConstTable) -> (ReturnKind, Option<i64>, String) {
+    // compiled once since it is called for every return statement
+    static RE4CAST: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^\(\s*(?:const\s+)?\w+(?:\s*\*)*\s*\)\s*(?<rest>[\w(].*)$").unwrap()
+    });
+    static RE4VAR: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^[&\*]*\s*[A-Za-z_]\w*(?:\s*(?:\.|->)\s*\w+|\s*\[[^\]]*\])*$").unwrap()
+    });
+    static RE4CALL: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^(?<name>[A-Za-z_]\w*)\s*\(").unwrap());
+    static RE4STRING: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r#"^(?:(?:L|u8|u|U)?"(?:[^"\\]|\\.)*"\s*)+$"#).unwrap());
+    let re4cast = &*RE4CAST;
+    let re4var = &*RE4VAR;
+    let re4call = &*RE4CALL;
+    let re4string = &*RE4STRING;
+    let mut expr = expr.trim();
+    // strip enclosing parentheses and casts; ex) "((uint8_t)speed)" -> "speed"
+    loop {
+        if let Some(inner) = expr
+            .strip_prefix('(')
+            .and_then(|e| e.strip_suffix(')'))
+            .filter(|inner| closing_paren(expr, 0) == Some(expr.len() - 1) && !inner.is_empty())
+        {
+            expr = inner.trim();
+        } else if let Some(cap) = re4cast.captures(expr) {
+            expr = cap.name("rest").unwrap().as_str().trim();
+        } else {
+            break;
+        }
+    }
+    if expr.is_empty() {
+        return (ReturnKind::Void, None, String::new());
+    }
+    if let Some(value) = consts.eval(expr) {
+        let kind = match consts.is_value(expr) {
+            true => ReturnKind::EnumConstant,
+            false => ReturnKind::Literal,
+        };
+        return (kind, Some(value), String::new());
+    }
+    match expr {
+        "true" => return (ReturnKind::Literal, Some(1), String::new()),
+        "false" | "NULL" | "nullptr" => return (ReturnKind::Literal, Some(0), String::new()),
+        _ => {}
+    }
+    // macros which cannot be evaluated are still constants; ex) "E_NOT_OK"
+    if re4string.is_match(expr) || consts.contains(expr) {
+        (ReturnKind::Literal, None, String::new())
+    } else if re4var.is_match(expr) {
+        (ReturnKind::Variable, None, String::new())
+    } else if let Some(cap) = re4call
+        .captures(expr)
+        .filter(|c| closing_paren(expr, c.get(0).unwrap().end() - 1) == Some(expr.len() - 1))
+    {
+        (ReturnKind::Call, None, cap["name"].to_string())
+    } else {
+        (ReturnKind::Expression, None, String::new())
+    }
+}
+
+/// offset of the parenthesis closing the one at the given offset
+///
+fn closing_paren(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.get(open..)?.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// true if the end of the given block is not reachable; the last statement returns, jumps or never ends,
+/// or it is an if-else chain of which all the branches do so
+///
+fn ends_with_return(block: &str) -> bool {
+    let Some((header, body, rest)) = last_branch(block) else {
+        return false;
+    };
+    // compiled once since it is called recursively for every block
+    static RE4FOREVER: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^(?:while\s*\(\s*(?:1|true|TRUE)\s*\)|for\s*\(\s*;\s*;\s*\))$").unwrap()
+    });
+    static RE4TERMINAL: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^(?:return\b|goto\b|(?:abort|exit|_Exit|quick_exit|longjmp|siglongjmp|__builtin_unreachable)\s*\()").unwrap()
+    });
+    static RE4DEFAULT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bdefault\s*:").unwrap());
+    let re4forever = &*RE4FOREVER;
+    if header.is_empty() {
+        let re4terminal = &*RE4TERMINAL;
+        let body = body.trim();
+        return match body.strip_prefix('{') {
+            Some(inner) => ends_with_return(inner.strip_suffix('}').unwrap_or(inner)),
+            // "do { ... } while (1);"
+            None => re4terminal.is_match(body) || re4forever.is_match(body),
+        };
+    }
+    if re4forever.is_match(header) {
+        return !has_break(body);
+    }
+    // "switch (x) { case 0: return 1; default: return 2; }" returns on every path
+    // if it has "default" and no "break", since cases fall through to the last statement
+    if header.starts_with("switch") {
+        let body = body.trim();
+        let inner = body.strip_prefix('{').unwrap_or(body);
+        let inner = inner.strip_suffix('}').unwrap_or(inner);
+        return RE4DEFAULT.is_match(&mask_nested_loops(inner))
+            && !has_break(inner)
+            && ends_with_return(inner);
+    }
+    if header != "else" {
+        return false;
+    }
+    // all the branches of "if (...) ... else if (...) ... else ..." should end with return
+    if !ends_with_branch(body) {
+        return false;
+    }
+    let mut rest = rest;
+    while let Some((header, body, before)) = last_branch(rest) {
+        if !header.starts_with("if") && !header.starts_with("else if") || !ends_with_branch(body) {
+            return false;
+        }
+        if header.starts_with("if") {
+            return true;
+        }
+        rest = before;
+    }
+    false
+}
+
+/// true if the body of a branch, either a block or a statement, ends with return
+///
+fn ends_with_branch(body: &str) -> bool {
+    let body = body.trim();
+    match body.strip_prefix('{') {
+        Some(inner) => ends_with_return(inner.strip_suffix('}').unwrap_or(inner)),
+        None => ends_with_return(&format!("{};", body)),
+    }
+}
+
+/// the last statement or block of the given code; (header, body, code before the header)
+/// such as ("if (x)", "{ return 1; }", "...") or ("else", "return 0", "...");
+/// a plain statement or block has an empty header
+///
+fn last_branch(code: &str) -> Option<(&str, &str, &str)> {
+    static RE4COND: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^(?:else\s+)?(?:if|while|for)\s*\(").unwrap());
+    let code = code.trim_end();
+    if let Some(text) = code.strip_suffix(';') {
+        let start = last_delimiter(text).map_or(0, |i| i + 1);
+        let stmt = text.get(start..).unwrap().trim_start();
+        let before = text.get(..text.len() - stmt.len()).unwrap();
+        let stmt = strip_labels(stmt);
+        // "else return 0;"
+        if let Some(after) = stmt.strip_prefix("else").filter(|after| {
+            after.starts_with(char::is_whitespace) && !after.trim_start().starts_with("if")
+        }) {
+            return Some(("else", after.trim(), before));
+        }
+        // "if (x) return 1;" or "do { ... } while (1);"
+        if let Some(m) = RE4COND.find(stmt) {
+            let close = closing_paren(stmt, m.end() - 1)?;
+            let header = stmt.get(..=close).unwrap();
+            return Some((header, stmt.get(close + 1..).unwrap().trim(), before));
+        }
+        Some(("", stmt, before))
+    } else if code.ends_with('}') {
+        let open = matching_open_brace(code, code.len() - 1)?;
+        let before = code.get(..open).unwrap();
+        let header_start = last_delimiter(before).map_or(0, |i| i + 1);
+        let header = strip_labels(before.get(header_start..).unwrap().trim());
+        Some((
+            header,
+            code.get(open..).unwrap(),
+            code.get(..header_start).unwrap(),
+        ))
+    } else {
+        None
+    }
+}
+
+/// statement without its leading labels; ex) "return 2" of "default: return 2"
+///
+fn strip_labels(stmt: &str) -> &str {
+    static RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^(?:case\b[^:;]*|default|[A-Za-z_]\w*)\s*:").unwrap());
+    let re = &*RE;
+    let mut stmt = stmt.trim_start();
+    while let Some(m) = re.find(stmt) {
+        // "std::exit(1)" is not a label
+        if stmt.get(m.end()..).unwrap().starts_with(':') {
+            break;
+        }
+        stmt = stmt.get(m.end()..).unwrap().trim_start();
+    }
+    stmt
+}
+
+/// true if the code has "break" leaving its own loop or switch rather than a nested one
+///
+fn has_break(code: &str) -> bool {
+    static RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bbreak\s*;").unwrap());
+    RE.is_match(&mask_nested_loops(code))
+}
+
+/// code with the nested loops and switches blanked; ex) "for (;;) { break; } return 0;"
+/// becomes "                       return 0;"
+///
+fn mask_nested_loops(code: &str) -> String {
+    static RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\b(?:(?:for|while|switch)\s*\(|do\b)").unwrap());
+    let re = &*RE;
+    let mut result = String::new();
+    let mut pos = 0;
+    while let Some(m) = re.find_at(code, pos) {
+        let mut end = m.end();
+        if m.as_str().ends_with('(') {
+            end = closing_paren(code, m.end() - 1).map_or(code.len(), |i| i + 1);
+        }
+        let rest = code.get(end..).unwrap();
+        let start = code.len() - rest.trim_start().len();
+        end = match rest.trim_start().chars().next() {
+            Some('{') => (find_end_of_func(code, start + 1) + 1).min(code.len()),
+            // "while (x);" of "do { ... } while (x);"
+            Some(';') => start + 1,
+            _ => rest.find(';').map_or(code.len(), |i| end + i + 1),
+        };
+        result.push_str(code.get(pos..m.start()).unwrap());
+        result.extend(std::iter::repeat_n(' ', end - m.start()));
+        pos = end;
+    }
+    result.push_str(code.get(pos..).unwrap());
+    result
+}
+
+/// offset of the last ';', '{' or '}' outside of parentheses
+///
+fn last_delimiter(code: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in code.char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' => depth -= 1,
+            ';' | '{' | '}' if depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// offset of the brace opening the one closed at the given offset
+///
+fn matching_open_brace(code: &str, close: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in code.get(..=close)?.char_indices().rev() {
+        match c {
+            '}' => depth += 1,
+            '{' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
     }
+    None
 }
 
-/// list of static variables from C source code
+/// list of non-static local variables declared in the given range of a function body
 ///
-fn get_static_vars(code: &str, fncs: &Vec<Function>) -> Vec<StaticVariable> {
+fn get_locals(
+    code: &str,
+    start: usize,
+    end: usize,
+    consts: &ConstTable,
+    type_names: &HashSet<String>,
+) -> Vec<LocalVariable> {
     let mut result = vec![];
-    let re = Regex::new(r"(?i)(?<keyword>static\s+|static\s+const\s+|const\s+static\s+)+(?<dtype>.*?)(?<name>\w+)\s*(?:\[(?<array_size>.*?)\])?\s*(?:=\s*(?<value>\{.*?\}|.*?))?;").unwrap();
-    for cap in re.captures_iter(code) {
-        let captured = cap.get(0).unwrap().as_str().trim().to_string();
-        let dtype = cap.name("dtype").unwrap().as_str().trim().to_string();
-        let name = cap.name("name").unwrap().as_str().trim().to_string();
-        let array_size = cap
-            .name("array_size")
-            .map_or(0, |c| c.as_str().parse().unwrap_or(0));
-        let init = cap
-            .name("value")
-            .map_or("0", |c| c.as_str().trim())
-            .to_string();
-        let is_const = cap
-            .name("keyword")
-            .map_or(false, |c| c.as_str().to_lowercase().contains("const"));
-        let name_expr = cap.name("array_size").map_or(name.clone(), |c| {
-            (name.clone() + "[" + c.as_str().trim() + "]").to_string()
-        });
-        let mut is_local = false;
-        let mut func_name = String::from("");
-        for func in fncs {
-            if let Some(pos) = code.find(func.captured.as_str()) {
-                let start = pos + code.get(pos..).unwrap().find('{').unwrap() + 1;
-                let stop = find_end_of_func(code, start);
-                let body = code.get(start..stop).unwrap();
-                if body.contains(captured.as_str()) {
-                    is_local = true;
-                    func_name = func.name.to_string();
-                }
-            }
+    let re = Regex::new(r"(?s)^(?<dtype>\w[\w\s\*]*?)\s*\b(?<name>\w+)\s*(?:\[(?<array_size>[^\]]*)\])?\s*(?:=\s*(?<value>.*))?$").unwrap();
+    let re4next = Regex::new(r"(?s)^(?<stars>[\s\*]*)(?<name>\w+)\s*(?:\[(?<array_size>[^\]]*)\])?\s*(?:=\s*(?<value>.*))?$").unwrap();
+    let mut lines = LineCounter::new(code);
+    for (offset, stmt) in split_statements(code, start, end) {
+        let declarators = split_args(stmt);
+        let Some(cap) = re.captures(declarators[0].trim()) else {
+            continue;
+        };
+        let dtype = cap["dtype"].trim().to_string();
+        let words: Vec<&str> = dtype
+            .split(|c: char| c.is_whitespace() || c == '*')
+            .collect();
+        // statements such as "return x" or declarations which are not local variables
+        if words.iter().any(|w| {
+            is_c_keyword(w)
+                || matches!(
+                    *w,
+                    "static" | "STATIC" | "extern" | "typedef" | "goto" | "break" | "continue"
+                )
+        }) {
+            continue;
+        }
+        // "x * y;" is a multiplication unless "x" is a known type, or it has an initializer or more declarators
+        let type_words: Vec<&str> = words
+            .iter()
+            .copied()
+            .filter(|w| !w.is_empty() && !matches!(*w, "const" | "volatile" | "restrict"))
+            .collect();
+        if dtype.contains('*')
+            && declarators.len() == 1
+            && cap.name("value").is_none()
+            && matches!(type_words[..], [word] if !is_known_type(word, type_names))
+        {
+            continue;
+        }
+        let base_type = dtype.trim_end_matches(['*', ' ']).to_string();
+        let line = lines.line_at(offset);
+        let mut push = |dtype: String, cap: &regex::Captures| {
+            let name = cap["name"].to_string();
+            let (array_size_expr, array_size) = get_array_size(cap, consts);
+            let name_expr = cap.name("array_size").map_or(name.clone(), |c| {
+                name.clone() + "[" + c.as_str().trim() + "]"
+            });
+            result.push(LocalVariable {
+                captured: stmt.trim().to_string(),
+                file: String::new(),
+                line,
+                name_expr,
+                name,
+                is_const: dtype.split_whitespace().any(|w| w == "const"),
+                dtype,
+                init: cap
+                    .name("value")
+                    .map_or("", |c| c.as_str().trim())
+                    .to_string(),
+                array_size_expr,
+                array_size,
+            });
+        };
+        push(dtype, &cap);
+        // following declarators share the base type; ex) "int a = 1, *b"
+        for declarator in &declarators[1..] {
+            let Some(cap) = re4next.captures(declarator.trim()) else {
+                break;
+            };
+            let stars = cap["stars"].replace(char::is_whitespace, "");
+            let dtype = match stars.is_empty() {
+                true => base_type.clone(),
+                false => format!("{} {}", base_type, stars),
+            };
+            push(dtype, &cap);
         }
-        result.push(StaticVariable {
-            captured: captured,
-            name_expr: name_expr,
-            name: name,
-            dtype: dtype,
-            is_local: is_local,
-            func_name: func_name,
-            init: init,
-            array_size: array_size,
-            is_const: is_const,
-        });
     }
     result
 }
 
-/// list of functions from C source code
+/// statements in the given range of code with their start offsets; split by ';', '{' and '}'
+/// outside of parentheses, where braces of initializers such as "= { 0 }" are a part of the statement
 ///
-fn get_fncs(code: &str) -> Vec<Function> {
+fn split_statements(code: &str, start: usize, end: usize) -> Vec<(usize, &str)> {
     let mut result = vec![];
-    let re = Regex::new(
-        r"((?<return>\w+[\w\s\*]*\s+)|FUNC\((?<return_ex>[^,]+),[^\)]+\)\s*)(?<name>\w+)[\w]*\s*\((?<args>[^=!><>;\(\)-]*)\)\s*\{"
-    ).unwrap();
-    let get_atypes = |args: String| -> (String, String) {
-        let mut type_list = String::new();
-        let mut name_list = String::new();
-        let mut first_pos = true;
-        let arg_list = args.split(',').collect::<Vec<&str>>();
-        for arg in arg_list {
-            let arg = arg.trim();
-            let re4sep = Regex::new(r"^(?<atype>.*?)(?<aname>\w+(?:\[.*?\])*)$").unwrap();
-            let mut atype;
-            let mut aname;
-            if let Some(cap) = re4sep.captures(&arg) {
-                atype = cap.name("atype").unwrap().as_str().trim().to_string();
-                aname = cap.name("aname").unwrap().as_str().trim().to_string();
-                // relocate 'const' only for 'datatype const *' -> 'const datatype *'
-                let re4const = Regex::new(r"\w[\s\r\n]+const[\s\r\n]*\*").unwrap();
-                if let Some(_) = re4const.captures(&atype) {
-                    atype = atype.replace("const", "");
-                    atype = format!("const {}", atype);
-                    let re4space = Regex::new(r"\s+").unwrap();
-                    atype = re4space.replace_all(&atype, " ").to_string();
-                }
-                if first_pos {
-                    first_pos = false;
-                } else {
-                    type_list.push_str(", ");
-                    name_list.push_str(", ");
-                }
-                type_list.push_str(&atype);
-                let array_dimension = aname.matches("[").count();
-                type_list.push_str(&"*".repeat(array_dimension));
-                // remove '[]' from name string
-                let re4bracket = Regex::new(r"(\[.*?\])+").unwrap();
-                if let Some(_) = re4bracket.captures(&aname) {
-                    aname = re4bracket.replace_all(&aname, "").to_string();
+    let text = code.get(start..end).unwrap();
+    let mut depth = 0;
+    let mut init_depth = 0;
+    let mut begin = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '{' if init_depth > 0 || text.get(..i).unwrap().trim_end().ends_with('=') => {
+                init_depth += 1
+            }
+            '}' if init_depth > 0 => init_depth -= 1,
+            ';' | '{' | '}' if depth == 0 && init_depth == 0 => {
+                let stmt = text.get(begin..i).unwrap();
+                let trimmed = stmt.trim_start();
+                if !trimmed.is_empty() {
+                    result.push((start + begin + stmt.len() - trimmed.len(), trimmed));
                 }
-                name_list.push_str(&aname);
+                begin = i + 1;
             }
+            _ => {}
         }
-        if type_list.trim() == "void" {
-            type_list.clear();
-            name_list.clear();
-        }
-        (type_list, name_list)
-    };
-    for cap in re.captures_iter(code) {
-        if cap.name("name").unwrap().as_str().trim() == "if" {
-            continue;
-        }
-        let re4space = Regex::new(r"\s+").unwrap();
-        let mut raw_args = re4space
-            .replace_all(cap.name("args").unwrap().as_str().trim(), " ")
-            .replace("\\", "")
-            .trim()
-            .to_string();
-        if raw_args.trim() == "void" {
-            raw_args.clear();
-        }
-        let (atypes, anames) = get_atypes(raw_args.clone());
-        let rtype = cap
-            .name("return")
-            .or(cap.name("return_ex"))
-            .unwrap()
-            .as_str()
-            .replace("static", "")
-            .replace("STATIC", "")
-            .replace("inline", "")
-            .replace("INLINE", "")
-            .trim()
-            .to_string();
-        result.push(Function {
-            captured: cap.get(0).unwrap().as_str().trim().to_string(),
-            name: cap.name("name").unwrap().as_str().trim().to_string(),
-            is_local: cap
-                .get(0)
-                .unwrap()
-                .as_str()
-                .to_ascii_lowercase()
-                .contains("static"),
-            rtype: rtype,
-            args: raw_args.clone(),
-            atypes: atypes,
-            anames: anames,
-        });
     }
     result
 }
@@ -356,7 +2646,7 @@ fn get_fncs(code: &str) -> Vec<Function> {
 fn find_end_of_func(code: &str, start: usize) -> usize {
     let mut stop = start;
     let mut open = 1;
-    for (i, c) in code.get(start..).unwrap().chars().enumerate() {
+    for (i, c) in code.get(start..).unwrap().char_indices() {
         if c == '}' {
             open -= 1;
         } else if c == '{' {
@@ -393,6 +2683,146 @@ fn get_ncls(code: &str, fncs: &Vec<Function>) -> Vec<NestedCall> {
     result
 }
 
+/// list of calls to functions not defined in C source code
+///
+fn get_ext_calls(
+    code: &str,
+    fncs: &Vec<Function>,
+    protos: &[Function],
+    macros: &[Macro],
+    lsv_macros: &[LsvMacro],
+) -> Vec<ExternalCall> {
+    let mut result = vec![];
+    let re = Regex::new(r"\b(?<name>[A-Za-z_]\w*)\s*\(").unwrap();
+    // function pointer declarator following its type; ex) "(*cb)(" of "void (*cb)(int)"
+    let re4declarator = Regex::new(r"^\(\s*\*+\s*\w+\s*\)\s*\(").unwrap();
+    let mut lines = LineCounter::new(code);
+    for caller in fncs {
+        if let Some(pos) = code.find(caller.captured.as_str()) {
+            let start = pos + code.get(pos..).unwrap().find('{').unwrap() + 1;
+            let stop = find_end_of_func(code, start);
+            // names in string and char literals are not calls
+            let body = blank_literals(code.get(start..stop).unwrap());
+            let declared = get_declared_names(caller, &body);
+            for cap in re.captures_iter(&body) {
+                let name = cap.name("name").unwrap().as_str();
+                let paren = cap.get(0).unwrap().end() - 1;
+                // calls through function pointers of parameters or local variables
+                if declared.contains(name)
+                    || re4declarator.is_match(&body[paren..])
+                    || is_c_keyword(name)
+                    || lsv_macros.iter().any(|m| m.name == name)
+                    || find_func_in_list(name, fncs)
+                    || macros.iter().any(|m| m.name == name)
+                {
+                    continue;
+                }
+                result.push(ExternalCall {
+                    name: name.to_string(),
+                    file: String::new(),
+                    line: lines.line_at(start + cap.get(0).unwrap().start()),
+                    caller: caller.clone(),
+                    proto: protos.iter().find(|p| p.name == name).cloned(),
+                });
+            }
+        }
+    }
+    result
+}
+
+/// names of the parameters and local variables of the function including function pointers
+/// such as "void (*cb)(int)", which are called like functions in the body
+///
+fn get_declared_names(fnc: &Function, body: &str) -> HashSet<String> {
+    static RE4FNPTR: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\(\s*\*+\s*(?<name>\w+)\s*\)\s*\(").unwrap());
+    let mut result: HashSet<String> = fnc
+        .anames
+        .split(',')
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    result.extend(fnc.locals.iter().map(|local| local.name.clone()));
+    for text in [fnc.args.as_str(), body] {
+        result.extend(
+            RE4FNPTR
+                .captures_iter(text)
+                .map(|cap| cap["name"].to_string()),
+        );
+    }
+    result
+}
+
+/// replace the contents of string and char literals with spaces; the offsets are kept
+///
+fn blank_literals(code: &str) -> String {
+    let mut result = String::with_capacity(code.len());
+    let mut quote = None;
+    let mut escaped = false;
+    let mut prev = ' ';
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q || c == '\n' {
+                quote = None;
+                prev = c;
+                result.push(c);
+                continue;
+            }
+            result.push_str(&" ".repeat(c.len_utf8()));
+            continue;
+        }
+        // a quote between digits is a digit separator; ex) 1'000
+        let is_separator =
+            c == '\'' && prev.is_ascii_digit() && chars.peek().is_some_and(|n| n.is_ascii_digit());
+        if (c == '"' || c == '\'') && !is_separator {
+            quote = Some(c);
+        }
+        prev = c;
+        result.push(c);
+    }
+    result
+}
+
+/// true if the word is a C keyword or an operator-like builtin which can be followed by '('
+///
+fn is_c_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "if" | "for"
+            | "while"
+            | "switch"
+            | "return"
+            | "sizeof"
+            | "do"
+            | "else"
+            | "case"
+            | "defined"
+            | "alignof"
+            | "_Alignof"
+            | "typeof"
+            | "__typeof__"
+            | "asm"
+            | "__asm"
+            | "volatile"
+            | "__volatile__"
+            | "__asm__"
+            | "__attribute__"
+            | "_Static_assert"
+            | "static_assert"
+            | "_Generic"
+            | "catch"
+            | "decltype"
+            | "noexcept"
+            | "typeid"
+            | "alignas"
+    )
+}
+
 fn find_func_in_list(funcname: &str, fncs: &Vec<Function>) -> bool {
     let mut result = false;
     for fnc in fncs {
@@ -467,6 +2897,19 @@ static inline char local_function(int a,
     fn test_remove_comments() {
         let clean_code = remove_comments(TEST_CODE);
         assert!(!clean_code.contains("test-comment"));
+        // quotes of prefixed and raw literals, and digit separators
+        let code = "\
+static const wchar_t *url = L\"http://example.com\";
+const char *path = R\"x(\"//\")x\"; // raw
+long count = 1'000'000; // count
+char slash = '/'; /* slash */
+";
+        let clean_code = remove_comments(code);
+        assert!(clean_code.contains("L\"http://example.com\";"));
+        assert!(clean_code.contains("R\"x(\"//\")x\";"));
+        assert!(!clean_code.contains("raw"));
+        assert!(!clean_code.contains("count\n"));
+        assert!(!clean_code.contains("/* slash */"));
     }
 
     #[test]
@@ -479,7 +2922,8 @@ static inline char local_function(int a,
     #[test]
     fn test_get_static_vars() {
         let list_fncs = get_fncs(TEST_CODE);
-        let list_static_vars = get_static_vars(TEST_CODE, &list_fncs);
+        let consts = ConstTable::default();
+        let list_static_vars = get_static_vars(TEST_CODE, &list_fncs, &consts);
         assert_eq!(list_static_vars[0].name, "static_var");
         assert_eq!(list_static_vars[0].dtype, "char");
         assert_eq!(list_static_vars[0].is_local, false);
@@ -490,6 +2934,586 @@ static inline char local_function(int a,
         assert_eq!(list_static_vars[1].func_name, "local_function");
     }
 
+    #[test]
+    fn test_static_var_array_size() {
+        let code = "\
+#define BUF_LEN 8
+typedef enum { ChA, ChB, ChMax } Channel_t;
+static uint8_t buf[BUF_LEN * 2];
+static uint16_t samples[ChMax * sizeof(uint32_t)];
+static uint8_t unknown[EXTERNAL_LEN];
+static uint8_t scalar;
+";
+        let parser = Parser::parse(code);
+        assert_eq!(parser.static_vars[0].array_size_expr, "BUF_LEN * 2");
+        assert_eq!(parser.static_vars[0].array_size, Some(16));
+        assert_eq!(parser.static_vars[1].array_size, Some(8));
+        assert_eq!(parser.static_vars[2].array_size_expr, "EXTERNAL_LEN");
+        assert_eq!(parser.static_vars[2].array_size, None);
+        assert_eq!(parser.static_vars[3].array_size, None);
+    }
+
+    #[test]
+    fn test_static_var_initializer() {
+        let code = "\
+typedef struct { uint8_t id; uint16_t gain[2]; } Calib_t;
+static const Calib_t calib[] = {
+    { .id = 1, .gain = { 10, 20 } },
+    { .id = 2, .gain = { 30, 40 } },
+};
+";
+        let parser = Parser::parse(code);
+        let var = &parser.static_vars[0];
+        assert_eq!(var.name, "calib");
+        assert!(var.init.starts_with('{') && var.init.ends_with('}'));
+        let Initializer::List { items } = &var.initializer else {
+            panic!("list expected");
+        };
+        assert_eq!(items.len(), 2);
+        let Initializer::List { items: fields } = &items[1].value else {
+            panic!("list expected");
+        };
+        assert_eq!(fields[0].field.as_deref(), Some("id"));
+        assert_eq!(fields[1].field.as_deref(), Some("gain"));
+
+        let code = "static const char *t[] = {\"a;b\", \"}\"};\nconst char seps[] = {';', '{'};\n";
+        let parser = Parser::parse(code);
+        assert_eq!(parser.static_vars[0].init, "{\"a;b\", \"}\"}");
+        assert_eq!(parser.global_vars[0].init, "{';', '{'}");
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let code = "\
+#ifndef MOTOR_H
+#define MOTOR_H
+#include <stdint.h>
+#define MOTOR_COUNT 2U
+#define MOTOR_SPEED(pct) ((pct) * 10)
+#ifdef __cplusplus
+extern \"C\" {
+#endif
+struct motor_state { uint8_t id; uint16_t speed; };
+typedef enum { Idle, Forward } Direction_t;
+extern uint8_t motorPins[MOTOR_COUNT];
+extern const Direction_t defaultDir;
+extern uint32_t getCurrentTime(void);
+void setDir(const Direction_t dir);
+FUNC(void, MOTOR_CODE) Motor_MainFunction(void);
+static inline uint8_t isIdle(Direction_t dir)
+{
+    return dir == Idle;
+}
+#ifdef __cplusplus
+}
+#endif
+#endif
+";
+        let options = ParserOptions {
+            kind: SourceKind::Header,
+            ..Default::default()
+        };
+        let parser = Parser::parse_with_options(code, &options);
+        let names: Vec<&str> = parser.fncs.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["isIdle", "getCurrentTime", "setDir", "Motor_MainFunction"]
+        );
+        assert!(parser.fncs.iter().all(|f| f.is_public));
+        assert!(parser.fncs[0].is_inline);
+        assert_eq!(parser.fncs[1].rtype, "uint32_t");
+        assert_eq!(parser.fncs[2].atypes, "const Direction_t");
+        assert_eq!(parser.fncs[3].rtype, "void");
+        assert_eq!(parser.protos.len(), 3);
+        assert_eq!(parser.extern_vars[0].name, "motorPins");
+        assert_eq!(parser.extern_vars[0].array_size, Some(2));
+        assert_eq!(parser.extern_vars[1].dtype, "const Direction_t");
+        assert!(parser.extern_vars[1].is_const);
+        assert_eq!(parser.macros[1].name, "MOTOR_COUNT");
+        assert_eq!(parser.macros[1].int_value, Some(2));
+        assert!(parser.macros[2].is_function_like);
+        assert_eq!(parser.macros[2].params, ["pct"]);
+        assert_eq!(parser.macros[2].value, "((pct) * 10)");
+        assert_eq!(
+            parser.typedefs[1].captured,
+            "struct motor_state { uint8_t id; uint16_t speed; };"
+        );
+    }
+
+    #[test]
+    fn test_source_kind_from_path() {
+        assert_eq!(
+            SourceKind::from_path(Path::new("motor.h")),
+            SourceKind::Header
+        );
+        assert_eq!(
+            SourceKind::from_path(Path::new("motor.c")),
+            SourceKind::Source
+        );
+    }
+
+    #[test]
+    fn test_function_body_and_locals() {
+        let code = "\
+/* speed */
+static int speed;
+int getSpeed(int gain) // café
+{
+    /* scaled */
+    const int scaled = speed * gain, *p = &scaled;
+    uint8_t buf[4] = { 0, 1 }, n;
+    static int calls = 0;
+    for (n = 0; n < 4; n++) {
+        int inner = buf[n];
+    }
+    Point *pt;
+    gain * scaled;
+    return *p;
+}
+typedef struct { int x; } Point;
+";
+        let parser = Parser::parse(code);
+        let fnc = &parser.fncs[0];
+        assert!(fnc.body.starts_with("{\n    /* scaled */"));
+        assert!(fnc.body.ends_with("return *p;\n}"));
+        let span = fnc.body_span.as_ref().unwrap();
+        assert_eq!(&code[span.start..span.end], fnc.body);
+        assert_eq!((span.start_line, span.end_line), (4, 15));
+        let locals: Vec<(&str, &str, &str)> = fnc
+            .locals
+            .iter()
+            .map(|v| (v.dtype.as_str(), v.name_expr.as_str(), v.init.as_str()))
+            .collect();
+        assert_eq!(
+            locals,
+            [
+                ("const int", "scaled", "speed * gain"),
+                ("const int *", "p", "&scaled"),
+                ("uint8_t", "buf[4]", "{ 0, 1 }"),
+                ("uint8_t", "n", ""),
+                ("int", "inner", "buf[n]"),
+                // "gain * scaled" is a multiplication rather than a declaration
+                ("Point *", "pt", "")
+            ]
+        );
+        assert_eq!(fnc.locals[1].line, 6);
+        assert_eq!(fnc.locals[2].array_size, Some(4));
+    }
+
+    #[test]
+    fn test_annotations() {
+        let code = "\
+// cyagen-file: tag=driver
+// cyagen: tag=\"safety critical\", mock=real
+static int count = 0;
+int limit = 10; /* cyagen: skip */
+extern int ticks; // cyagen: mock
+// cyagen: skip
+
+void helper(void)
+{
+    count++;
+}
+// cyagen: mock=real
+void run(void)
+{
+    helper();
+    send(count);
+}
+const char *banner = \"// cyagen: skip\";
+static const wchar_t *url = L\"http://example.com\";
+";
+        let parser = Parser::parse(code);
+        assert_eq!(parser.static_vars[1].name, "url");
+        assert_eq!(parser.static_vars[1].init, "L\"http://example.com\"");
+        assert_eq!(parser.annotations["tag"], "driver");
+        assert_eq!(parser.static_vars[0].annotations["tag"], "safety critical");
+        assert_eq!(parser.static_vars[0].annotations["mock"], "real");
+        // annotations in string literals are ignored
+        assert_eq!(parser.global_vars.len(), 1);
+        assert_eq!(parser.global_vars[0].name, "banner");
+        assert!(parser.global_vars[0].annotations.is_empty());
+        assert_eq!(parser.extern_vars[0].annotations["mock"], "true");
+        assert_eq!(parser.fncs.len(), 1);
+        assert_eq!(parser.fncs[0].name, "run");
+        assert_eq!(parser.fncs[0].annotations["mock"], "real");
+        assert!(parser.ncls.is_empty());
+        assert_eq!(parser.ext_calls[0].caller.annotations["mock"], "real");
+    }
+
+    #[test]
+    fn test_lsv_macros() {
+        let code = "\
+TESTABLE_STATIC(uint8_t, counter, 0U);
+void tick(void)
+{
+    TESTABLE_STATIC(uint16_t, history[4], {0});
+    LOCAL_STATIC_VARIABLE(tick, int, calls, 0);
+    counter++;
+}
+";
+        let options = ParserOptions {
+            lsv_macros: vec![
+                "TESTABLE_STATIC(type, name, init)".parse().unwrap(),
+                "LOCAL_STATIC_VARIABLE".parse().unwrap(),
+            ],
+            ..Default::default()
+        };
+        let parser = Parser::parse_with_options(code, &options);
+        assert_eq!(parser.lsv_macro_name, "TESTABLE_STATIC");
+        let vars: Vec<(&str, &str, bool)> = parser
+            .static_vars
+            .iter()
+            .map(|v| (v.name.as_str(), v.func_name.as_str(), v.is_local))
+            .collect();
+        assert_eq!(
+            vars,
+            [
+                ("counter", "", false),
+                ("history", "tick", true),
+                ("calls", "tick", true)
+            ]
+        );
+        assert_eq!(parser.static_vars[1].array_size, Some(4));
+        assert_eq!(parser.static_vars[0].init, "0U");
+        assert_eq!(parser.static_vars[2].lsv_macro, "LOCAL_STATIC_VARIABLE");
+        assert!(parser.ext_calls.is_empty());
+        // layouts to redefine the macros in templates
+        assert_eq!(parser.lsv_macros[0].args, ["type", "name", "init"]);
+        assert_eq!(parser.lsv_macros[0].params, "datatype, varname, initvalue");
+        assert!(!parser.lsv_macros[0].has_func);
+        assert_eq!(
+            parser.lsv_macros[1].params,
+            "funcname, datatype, varname, initvalue"
+        );
+        assert!(parser.lsv_macros[1].has_func);
+        let layout = LsvLayout::from(&"M(_, type, name, _)".parse::<LsvMacro>().unwrap());
+        assert_eq!(layout.params, "unused0, datatype, varname, unused3");
+        assert!("BAD(func, type)".parse::<LsvMacro>().is_err());
+        assert_eq!(
+            String::from(LsvMacro::default()),
+            "LOCAL_STATIC_VARIABLE(func, type, name, init)"
+        );
+    }
+
+    #[test]
+    fn test_extractors() {
+        let code = "\
+REGISTER_TASK(MotorTask, 3);
+/* DEM_EVENT(COMMENTED, 1) */
+void MotorTask(void)
+{
+    if (stalled()) {
+        DEM_EVENT(DEM_MOTOR_STALL, 2);
+    }
+}
+";
+        let options = ParserOptions {
+            extractors: vec![
+                Extractor {
+                    name: "tasks".to_string(),
+                    pattern: r"REGISTER_TASK\(\s*(?<name>\w+)\s*,\s*(?<prio>\d+)\s*\)".to_string(),
+                    scope: ExtractorScope::File,
+                },
+                Extractor {
+                    name: "dem_events".to_string(),
+                    pattern: r"DEM_EVENT\((?<id>\w+)\s*,\s*(?<level>\w+)\)".to_string(),
+                    scope: ExtractorScope::Function,
+                },
+            ],
+            ..Default::default()
+        };
+        let parser = Parser::parse_with_options(code, &options);
+        let tasks = &parser.extracted["tasks"];
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].groups["name"], "MotorTask");
+        assert_eq!(tasks[0].groups["prio"], "3");
+        assert_eq!(tasks[0].func_name, "");
+        let events = &parser.extracted["dem_events"];
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].groups["id"], "DEM_MOTOR_STALL");
+        assert_eq!(events[0].func_name, "MotorTask");
+        assert_eq!(events[0].line, 6);
+        let span = &events[0].span;
+        assert_eq!(&code[span.start..span.end], "DEM_EVENT(DEM_MOTOR_STALL, 2)");
+    }
+
+    #[test]
+    fn test_function_returns() {
+        let code = "\
+typedef enum { Idle = 0, Forward } Direction_t;
+#define E_OK 0
+#define E_NOT_OK ((Std_ReturnType)1u)
+int check(int x)
+{
+    if (x > 0) {
+        return E_OK;
+    } else if (x < 0) {
+        return (int)E_NOT_OK;
+    } else {
+        return Forward;
+    }
+}
+int get(int x)
+{
+    if (x) return buf[x];
+    else return getValue(x, 2);
+}
+int next(int x)
+{
+    if (x) {
+        return x + 1;
+    }
+}
+void stop(void)
+{
+    return;
+}
+int mode(int x)
+{
+    switch (x) {
+    case 0:
+        return 1;
+    default:
+        for (;;) {
+            break;
+        }
+        return 2;
+    }
+}
+int pick(int x)
+{
+    switch (x) {
+    case 0: return 1;
+    case 1: break;
+    default: return 2;
+    }
+}
+int wait(int x)
+{
+    switch (x) { case 0: return 1; }
+}
+";
+        let parser = Parser::parse(code);
+        let returns: Vec<(&str, ReturnKind, Option<i64>)> = parser.fncs[0]
+            .returns
+            .iter()
+            .map(|r| (r.expr.as_str(), r.kind, r.value))
+            .collect();
+        assert_eq!(
+            returns,
+            [
+                ("E_OK", ReturnKind::Literal, Some(0)),
+                ("(int)E_NOT_OK", ReturnKind::Literal, None),
+                ("Forward", ReturnKind::EnumConstant, Some(1))
+            ]
+        );
+        assert_eq!(parser.fncs[0].returns[2].line, 11);
+        assert_eq!(parser.fncs[1].returns[0].kind, ReturnKind::Variable);
+        assert_eq!(parser.fncs[1].returns[1].kind, ReturnKind::Call);
+        assert_eq!(parser.fncs[1].returns[1].callee, "getValue");
+        assert_eq!(parser.fncs[2].returns[0].kind, ReturnKind::Expression);
+        assert_eq!(parser.fncs[3].returns[0].kind, ReturnKind::Void);
+        let fall_through: Vec<bool> = parser.fncs.iter().map(|f| f.may_fall_through).collect();
+        assert_eq!(fall_through, [false, false, true, false, false, true, true]);
+    }
+
+    #[test]
+    fn test_parse_embedded() {
+        let code = "\
+#define MOTOR_START_SEC_VAR_CLEARED_8
+#include \"Motor_MemMap.h\"
+static uint8 counter;
+#define MOTOR_STOP_SEC_VAR_CLEARED_8
+#include \"Motor_MemMap.h\"
+#define MOTOR_START_SEC_CODE
+#include \"Motor_MemMap.h\"
+ISR(TIMER0_vect)
+{
+    counter++;
+    __asm__ volatile (\"nop\");
+}
+#pragma vector=TIMER0_A0_VECTOR
+__interrupt void Timer_A(void)
+{
+}
+void __attribute__((interrupt(\"IRQ\"))) irq_handler(void)
+{
+#pragma GCC unroll 4
+    for (int i = 0; i < 4; i++) { counter++; }
+}
+#define MOTOR_STOP_SEC_CODE
+#include \"Motor_MemMap.h\"
+#pragma section \".text_fast\"
+static int fast(int a)
+{
+    return a;
+}
+";
+        let parser = Parser::parse(code);
+        let isrs: Vec<(&str, &str, bool)> = parser
+            .fncs
+            .iter()
+            .map(|f| (f.name.as_str(), f.rtype.as_str(), f.is_isr))
+            .collect();
+        assert_eq!(
+            isrs,
+            [
+                ("TIMER0_vect", "void", true),
+                ("Timer_A", "void", true),
+                ("irq_handler", "void", true),
+                ("fast", "int", false)
+            ]
+        );
+        assert_eq!(parser.fncs[0].section, "CODE");
+        assert_eq!(parser.fncs[3].section, ".text_fast");
+        assert_eq!(parser.static_vars[0].section, "VAR_CLEARED_8");
+        assert_eq!(parser.pragmas.len(), 3);
+        assert_eq!(parser.pragmas[1].text, "GCC unroll 4");
+        assert_eq!(parser.pragmas[1].func_name, "irq_handler");
+        assert_eq!(parser.asm_blocks.len(), 1);
+        assert_eq!(parser.asm_blocks[0].code, "\"nop\"");
+        assert_eq!(parser.asm_blocks[0].func_name, "TIMER0_vect");
+        assert!(parser.ext_calls.is_empty());
+    }
+
+    #[test]
+    fn test_parse_cpp() {
+        let code = "\
+extern \"C\" {
+int c_init(void)
+{
+    return 0;
+}
+}
+namespace drv {
+class Motor : public Base {
+public:
+    Motor(int id) : id_(id) {}
+    int id() const { return id_; }
+};
+const std::vector<int> &Motor::history(const std::map<int, int> &table, int n) const
+{
+    if (n > 0) {
+        return cache_;
+    }
+    return cache_;
+}
+template <typename T>
+T clamp(T value, T lo, T hi)
+{
+    return value < lo ? lo : value;
+}
+}
+TEST_F(MotorTest, create)
+{
+}
+";
+        let options = ParserOptions {
+            language: Language::from_path(Path::new("motor.cpp")),
+            ..Default::default()
+        };
+        let parser = Parser::parse_with_options(code, &options);
+        let names: Vec<&str> = parser
+            .fncs
+            .iter()
+            .map(|f| f.qualified_name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "c_init",
+                "drv::Motor::Motor",
+                "drv::Motor::id",
+                "drv::Motor::history",
+                "drv::clamp"
+            ]
+        );
+        let history = &parser.fncs[3];
+        assert_eq!(history.name, "history");
+        assert_eq!(history.namespace, "drv");
+        assert_eq!(history.class, "Motor");
+        assert_eq!(history.rtype, "const std::vector<int> &");
+        assert_eq!(history.atypes, "const std::map<int, int> &, int");
+        assert_eq!(history.anames, "table, n");
+        assert_eq!(parser.fncs[1].rtype, "");
+        assert_eq!(parser.fncs[4].class, "");
+    }
+
+    #[test]
+    fn test_parse_preprocessed() {
+        let code = "\
+# 0 \"motor.c\"
+# 0 \"<built-in>\"
+# 1 \"motor.h\" 1
+typedef unsigned char uint8_t;
+extern void controlPin(uint8_t pin, uint8_t high);
+static inline uint8_t clamp(uint8_t v) { return v > 1 ? 1 : v; }
+# 2 \"motor.c\" 2
+
+static uint8_t pins[(2 * 4)];
+
+void setPin(uint8_t pin)
+{
+    controlPin(pin, clamp(1));
+}
+";
+        let mut options = ParserOptions {
+            preprocessed: true,
+            ..Default::default()
+        };
+        let parser = Parser::parse_with_options(code, &options);
+        assert_eq!(parser.fncs.len(), 1);
+        assert_eq!(parser.fncs[0].name, "setPin");
+        assert_eq!(parser.fncs[0].file, "motor.c");
+        assert_eq!(parser.fncs[0].line, 5);
+        assert_eq!(parser.static_vars[0].line, 3);
+        assert_eq!(parser.static_vars[0].array_size, Some(8));
+        assert!(parser.protos.is_empty());
+        assert!(parser.typedefs.is_empty());
+        assert_eq!(parser.ncls[0].callee.name, "clamp");
+        assert_eq!(parser.ncls[0].callee.file, "motor.h");
+        assert_eq!(parser.callees[0].name, "clamp");
+        assert_eq!(parser.callees[0].file, "motor.h");
+
+        options.all_origins = true;
+        let parser = Parser::parse_with_options(code, &options);
+        assert_eq!(parser.fncs.len(), 2);
+        assert_eq!(parser.protos[0].name, "controlPin");
+        assert_eq!(parser.protos[0].file, "motor.h");
+        assert_eq!(parser.protos[0].line, 2);
+    }
+
+    #[test]
+    fn test_element_lines() {
+        let parser = Parser::parse(TEST_CODE);
+        assert_eq!(parser.incs[0].line, 1);
+        assert_eq!(parser.incs[1].line, 3);
+        assert_eq!(parser.static_vars[0].line, 6);
+        assert_eq!(parser.fncs[0].line, 16);
+        assert_eq!(parser.fncs[1].line, 30);
+    }
+
+    #[test]
+    fn test_parse_with_defines() {
+        let code = "\
+static uint8_t buf[BUF_LEN];
+static uint8_t dbg[DEBUG_LEN];
+";
+        let options = ParserOptions {
+            macros: vec![
+                MacroOp::Undefine("BUF_LEN".to_string()),
+                MacroOp::Define("BUF_LEN".to_string(), "(4 * 8)".to_string()),
+                MacroOp::Define("DEBUG_LEN".to_string(), "1".to_string()),
+                MacroOp::Undefine("DEBUG_LEN".to_string()),
+            ],
+            ..Default::default()
+        };
+        let parser = Parser::parse_with_options(code, &options);
+        assert_eq!(parser.static_vars[0].array_size, Some(32));
+        assert_eq!(parser.static_vars[1].array_size, None);
+    }
+
     #[test]
     fn test_get_fncs() {
         let list_fncs = get_fncs(TEST_CODE);
@@ -0,0 +1,246 @@
+use super::parser::Parser;
+use serde::{Deserialize, Serialize};
+
+/// kind of a symbol shared between modules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Function,
+    Variable,
+}
+
+/// identifiers in the list of "symbols"; external definition in a module
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Symbol {
+    /// name; ex) "setDir"
+    pub name: String,
+    /// function or variable
+    pub kind: SymbolKind,
+    /// sourcename of the module where the symbol is defined; ex) "motor"
+    pub module: String,
+    /// line number of the definition
+    pub line: usize,
+}
+
+/// identifiers in the list of "links"; reference from a module to a symbol of another module
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolLink {
+    /// name of the referenced symbol; ex) "getCurrentTime"
+    pub name: String,
+    /// function or variable
+    pub kind: SymbolKind,
+    /// sourcename of the referencing module; ex) "motor"
+    pub module: String,
+    /// function where the call is made; empty for extern variables
+    pub caller: String,
+    /// line number of the reference
+    pub line: usize,
+    /// sourcenames of the modules defining the symbol; empty if undefined
+    pub defined_in: Vec<String>,
+}
+
+/// identifiers which can be used in project level template
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Project {
+    /// parsed source files
+    pub modules: Vec<Parser>,
+    /// list of external functions and variables defined in the modules
+    pub symbols: Vec<Symbol>,
+    /// list of external calls and extern variables linked to their defining modules
+    pub links: Vec<SymbolLink>,
+    /// names of referenced symbols which are not defined in any module
+    pub undefined: Vec<String>,
+    /// names of symbols which are defined in more than one module
+    pub multiply_defined: Vec<String>,
+}
+
+impl Project {
+    /// link the given modules with each other; each module should have its sourcename
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut timer = cyagen::Parser::parse("unsigned int getTime(void) { return 0; }");
+    /// timer.sourcename = "timer".to_string();
+    /// let mut motor = cyagen::Parser::parse("void move(void) { getTime(); }");
+    /// motor.sourcename = "motor".to_string();
+    /// let project = cyagen::Project::new(vec![timer, motor]);
+    /// assert_eq!(project.links[0].defined_in, ["timer"]);
+    /// ```
+    pub fn new(modules: Vec<Parser>) -> Self {
+        let symbols = get_symbols(&modules);
+        let links = get_links(&modules, &symbols);
+        let mut undefined: Vec<String> = vec![];
+        for link in &links {
+            if link.defined_in.is_empty() && !undefined.contains(&link.name) {
+                undefined.push(link.name.clone());
+            }
+        }
+        let mut multiply_defined: Vec<String> = vec![];
+        for symbol in &symbols {
+            let count = symbols.iter().filter(|s| s.name == symbol.name).count();
+            if count > 1 && !multiply_defined.contains(&symbol.name) {
+                multiply_defined.push(symbol.name.clone());
+            }
+        }
+        Self {
+            modules,
+            symbols,
+            links,
+            undefined,
+            multiply_defined,
+        }
+    }
+}
+
+/// list of non-static functions and variables defined in the modules
+///
+fn get_symbols(modules: &[Parser]) -> Vec<Symbol> {
+    let mut result = vec![];
+    for module in modules {
+        for fnc in &module.fncs {
+            // prototypes of a header are listed in fncs as well, but they are not definitions
+            if !fnc.is_local && fnc.captured.ends_with('{') {
+                result.push(Symbol {
+                    name: fnc.name.clone(),
+                    kind: SymbolKind::Function,
+                    module: module.sourcename.clone(),
+                    line: fnc.line,
+                });
+            }
+        }
+        for var in &module.global_vars {
+            result.push(Symbol {
+                name: var.name.clone(),
+                kind: SymbolKind::Variable,
+                module: module.sourcename.clone(),
+                line: var.line,
+            });
+        }
+    }
+    result
+}
+
+/// list of external calls and extern variables of the modules with their defining modules
+///
+fn get_links(modules: &[Parser], symbols: &[Symbol]) -> Vec<SymbolLink> {
+    let defined_in = |name: &str, kind: SymbolKind| -> Vec<String> {
+        symbols
+            .iter()
+            .filter(|s| s.name == name && s.kind == kind)
+            .map(|s| s.module.clone())
+            .collect()
+    };
+    let mut result = vec![];
+    for module in modules {
+        for call in &module.ext_calls {
+            result.push(SymbolLink {
+                name: call.name.clone(),
+                kind: SymbolKind::Function,
+                module: module.sourcename.clone(),
+                caller: call.caller.name.clone(),
+                line: call.line,
+                defined_in: defined_in(&call.name, SymbolKind::Function),
+            });
+        }
+        for var in &module.extern_vars {
+            // extern declaration of a variable defined in the same module is not a link
+            if module.global_vars.iter().any(|v| v.name == var.name) {
+                continue;
+            }
+            result.push(SymbolLink {
+                name: var.name.clone(),
+                kind: SymbolKind::Variable,
+                module: module.sourcename.clone(),
+                caller: String::new(),
+                line: var.line,
+                defined_in: defined_in(&var.name, SymbolKind::Variable),
+            });
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(sourcename: &str, code: &str) -> Parser {
+        let mut parser = Parser::parse(code);
+        parser.sourcename = sourcename.to_string();
+        parser
+    }
+
+    #[test]
+    fn test_project_links() {
+        let timer = module(
+            "timer",
+            "\
+unsigned int tickCount = 0;
+unsigned int getCurrentTime(void)
+{
+    return tickCount;
+}
+",
+        );
+        let motor = module(
+            "motor",
+            "\
+extern unsigned int tickCount;
+extern unsigned int getCurrentTime(void);
+void move(void)
+{
+    unsigned int now = getCurrentTime();
+    controlPin(1, 0);
+}
+",
+        );
+        let io = module(
+            "io",
+            "\
+void move(void)
+{
+}
+",
+        );
+        let project = Project::new(vec![timer, motor, io]);
+        assert_eq!(project.symbols.len(), 4);
+        assert_eq!(project.links.len(), 3);
+        assert_eq!(project.links[0].name, "getCurrentTime");
+        assert_eq!(project.links[0].caller, "move");
+        assert_eq!(project.links[0].defined_in, ["timer"]);
+        assert_eq!(project.links[1].name, "controlPin");
+        assert!(project.links[1].defined_in.is_empty());
+        assert_eq!(project.links[2].name, "tickCount");
+        assert_eq!(project.links[2].kind, SymbolKind::Variable);
+        assert_eq!(project.links[2].defined_in, ["timer"]);
+        assert_eq!(project.undefined, ["controlPin"]);
+        assert_eq!(project.multiply_defined, ["move"]);
+    }
+
+    #[test]
+    fn test_project_links_of_calls_only() {
+        let logger = module(
+            "logger",
+            "\
+typedef void (*Handler_t)(int);
+void notify(Handler_t cb, int code)
+{
+    Handler_t next = cb;
+    void (*fallback)(int) = next;
+    const char *fmt = \"retry(%d) failed\";
+    char open = '(';
+    cb(code);
+    next(code);
+    fallback(code);
+    writeLog(fmt);
+}
+",
+        );
+        let project = Project::new(vec![logger]);
+        assert_eq!(project.links.len(), 1);
+        assert_eq!(project.links[0].name, "writeLog");
+        assert_eq!(project.links[0].caller, "notify");
+        assert_eq!(project.undefined, ["writeLog"]);
+    }
+}
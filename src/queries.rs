@@ -0,0 +1,182 @@
+use super::filters::{base_type, TypeTable};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tera::Tera;
+
+/// register the functions querying the model of a parser; the model is the json data of the parser
+/// - `fnc_by_name(name)` : function defined in the source or null
+/// - `has_function(name)` : true if the function is defined in the source
+/// - `callers_of(name)` : functions calling the function
+/// - `callees_of(name)` : functions and external functions called by the function
+/// - `statics_of(name)` : local static variables of the function
+/// - `typedef_of(dtype)` : typedef defining the base type of the data type or null
+///
+pub(crate) fn register_functions(tera: &mut Tera, model: &Value, types: TypeTable) {
+    let model = Arc::new(model.clone());
+
+    let m = model.clone();
+    tera.register_function("fnc_by_name", move |args: &HashMap<String, Value>| {
+        let name = arg(args, "fnc_by_name", "name")?;
+        Ok(fnc_by_name(&m, name).cloned().unwrap_or(Value::Null))
+    });
+
+    let m = model.clone();
+    tera.register_function("has_function", move |args: &HashMap<String, Value>| {
+        let name = arg(args, "has_function", "name")?;
+        Ok(Value::Bool(fnc_by_name(&m, name).is_some()))
+    });
+
+    let m = model.clone();
+    tera.register_function("callers_of", move |args: &HashMap<String, Value>| {
+        let name = arg(args, "callers_of", "name")?;
+        let mut callers: Vec<&Value> = vec![];
+        let calls = list(&m, "ncls")
+            .filter(|ncl| is_named(&ncl["callee"], name))
+            .chain(list(&m, "ext_calls").filter(|call| call["name"] == name));
+        for call in calls {
+            let caller = &call["caller"];
+            // the defined function is preferred to the copy in the call
+            let caller = fnc_by_name(&m, text(&caller["name"])).unwrap_or(caller);
+            if !callers.iter().any(|c| c["name"] == caller["name"]) {
+                callers.push(caller);
+            }
+        }
+        Ok(Value::Array(callers.into_iter().cloned().collect()))
+    });
+
+    let m = model.clone();
+    tera.register_function("callees_of", move |args: &HashMap<String, Value>| {
+        let name = arg(args, "callees_of", "name")?;
+        let mut callees: Vec<&Value> = vec![];
+        let ncls = list(&m, "ncls")
+            .filter(|ncl| is_named(&ncl["caller"], name))
+            .map(|ncl| &ncl["callee"]);
+        let ext_calls = list(&m, "ext_calls").filter(|call| is_named(&call["caller"], name));
+        for callee in ncls.chain(ext_calls) {
+            if !callees.iter().any(|c| c["name"] == callee["name"]) {
+                callees.push(callee);
+            }
+        }
+        Ok(Value::Array(callees.into_iter().cloned().collect()))
+    });
+
+    let m = model.clone();
+    tera.register_function("statics_of", move |args: &HashMap<String, Value>| {
+        let name = arg(args, "statics_of", "name")?;
+        let vars = list(&m, "static_vars")
+            .filter(|var| var["func_name"] == name)
+            .cloned()
+            .collect();
+        Ok(Value::Array(vars))
+    });
+
+    let m = model;
+    tera.register_function("typedef_of", move |args: &HashMap<String, Value>| {
+        let dtype = arg(args, "typedef_of", "dtype")?;
+        let typedef = types
+            .index_of(&base_type(dtype))
+            .and_then(|index| list(&m, "typedefs").nth(index));
+        Ok(typedef.cloned().unwrap_or(Value::Null))
+    });
+}
+
+/// string argument of the function
+///
+fn arg<'a>(args: &'a HashMap<String, Value>, function: &str, key: &str) -> tera::Result<&'a str> {
+    args.get(key).and_then(Value::as_str).ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Function `{}` requires a string argument `{}`",
+            function, key
+        ))
+    })
+}
+
+fn list<'a>(model: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    model[key].as_array().into_iter().flatten()
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+/// true if the function has the name or the qualified name; ex) "Motor::run"
+///
+fn is_named(fnc: &Value, name: &str) -> bool {
+    fnc["name"] == name || fnc["qualified_name"] == name
+}
+
+fn fnc_by_name<'a>(model: &'a Value, name: &str) -> Option<&'a Value> {
+    list(model, "fncs").find(|fnc| is_named(fnc, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_queries() {
+        let code = "\
+typedef enum { Idle, Forward } Direction_t;
+static Direction_t dir = Idle;
+void setDir(const Direction_t d)
+{
+    static int count = 0;
+    count++;
+    dir = d;
+    controlPin(1);
+}
+void run(void)
+{
+    setDir(Forward);
+}
+";
+        let parser = Parser::parse(code);
+        let model = serde_json::to_value(&parser).unwrap();
+        let mut tera = Tera::default();
+        register_functions(&mut tera, &model, TypeTable::new(&parser.typedefs));
+        let render = |temp: &str| {
+            tera.clone()
+                .render_str(temp, &tera::Context::new())
+                .unwrap()
+        };
+        assert_eq!(
+            render(r#"{% set fnc = fnc_by_name(name="setDir") %}{{ fnc.rtype }}"#),
+            "void"
+        );
+        assert_eq!(
+            render(r#"{{ has_function(name="run") }} {{ has_function(name="stop") }}"#),
+            "true false"
+        );
+        assert_eq!(
+            render(r#"{{ callers_of(name="setDir") | map(attribute="name") | join(sep=",") }}"#),
+            "run"
+        );
+        assert_eq!(
+            render(
+                r#"{{ callers_of(name="controlPin") | map(attribute="name") | join(sep=",") }}"#
+            ),
+            "setDir"
+        );
+        assert_eq!(
+            render(r#"{{ callees_of(name="setDir") | map(attribute="name") | join(sep=",") }}"#),
+            "controlPin"
+        );
+        assert_eq!(
+            render(r#"{{ statics_of(name="setDir") | map(attribute="name") | join(sep=",") }}"#),
+            "count"
+        );
+        let temp =
+            r#"{% set typedef = typedef_of(dtype="const Direction_t *") %}{{ typedef.captured }}"#;
+        assert!(render(temp).starts_with("typedef enum"));
+        assert_eq!(
+            render(r#"{{ typedef_of(dtype="int") | json_encode }}"#),
+            "null"
+        );
+        assert!(tera
+            .clone()
+            .render_str("{{ fnc_by_name() }}", &tera::Context::new())
+            .is_err());
+    }
+}
@@ -0,0 +1,302 @@
+use super::encoding::{decode_text, read_text, write_text, LineEnding, TextEncoding, TextFormat};
+use anyhow::{anyhow, Context, Result};
+use include_dir::{include_dir, Dir};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// template sets embedded in the binary; each sub-directory is a set
+static BUILTIN_SETS: Dir = include_dir!("$CARGO_MANIFEST_DIR/example/templates");
+
+/// file of a template set
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateFile {
+    /// path to report errors; ex) "templates/test_@sourcename@.cc.njk" or "<builtin>/gtest/CMakeLists.txt.njk"
+    pub path: PathBuf,
+    /// template text with "\n" line endings
+    pub text: String,
+    /// line ending of the template file to be kept for new output files
+    pub line_ending: LineEnding,
+}
+
+/// template files by relative path with "/" such as ".vscode/tasks.json.njk"
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemplateSet {
+    /// template directory or "<builtin>/<name>"
+    pub dir: PathBuf,
+    /// files by relative path
+    pub files: BTreeMap<String, TemplateFile>,
+}
+
+impl TemplateSet {
+    /// all the files of the directory and its sub-directories
+    ///
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut set = Self {
+            dir: dir.to_path_buf(),
+            files: BTreeMap::new(),
+        };
+        set.collect(dir)?;
+        Ok(set)
+    }
+
+    fn collect(&mut self, dir: &Path) -> Result<()> {
+        let entries = fs::read_dir(dir)
+            .with_context(|| format!("failed to read folder `{}`", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.collect(&path)?;
+                continue;
+            }
+            let name = path
+                .strip_prefix(&self.dir)
+                .unwrap_or(&path)
+                .iter()
+                .map(|c| c.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let decoded = read_text(&path, TextEncoding::Auto)?;
+            let template = TemplateFile {
+                path,
+                text: decoded.text,
+                line_ending: decoded.format.line_ending,
+            };
+            self.files.insert(name, template);
+        }
+        Ok(())
+    }
+
+    /// names of the template sets embedded in the binary; ex) ["gtest"]
+    ///
+    pub fn builtin_names() -> Vec<&'static str> {
+        let mut names: Vec<&str> = BUILTIN_SETS
+            .dirs()
+            .filter_map(|dir| dir.path().to_str())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// template set embedded in the binary
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let set = cyagen::TemplateSet::builtin("gtest").unwrap();
+    /// assert!(set.files.contains_key("CMakeLists.txt.njk"));
+    /// ```
+    pub fn builtin(name: &str) -> Result<Self> {
+        let dir = BUILTIN_SETS.get_dir(name).ok_or_else(|| {
+            anyhow!(
+                "no built-in template set `{}`; available sets are [{}]",
+                name,
+                Self::builtin_names().join(", ")
+            )
+        })?;
+        let root = PathBuf::from("<builtin>");
+        let mut set = Self {
+            dir: root.join(name),
+            files: BTreeMap::new(),
+        };
+        let mut dirs = vec![dir];
+        while let Some(dir) = dirs.pop() {
+            dirs.extend(dir.dirs());
+            for file in dir.files() {
+                let name = file
+                    .path()
+                    .strip_prefix(name)
+                    .unwrap_or(file.path())
+                    .iter()
+                    .map(|c| c.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                // decoded the same way as the files of a directory
+                let decoded = decode_text(file.contents(), TextEncoding::Auto);
+                if decoded.had_errors {
+                    return Err(anyhow!("`{}` is not UTF-8", file.path().display()));
+                }
+                let template = TemplateFile {
+                    path: root.join(file.path()),
+                    text: decoded.text,
+                    line_ending: decoded.format.line_ending,
+                };
+                set.files.insert(name, template);
+            }
+        }
+        Ok(set)
+    }
+
+    /// files of the other set replace the ones of the same name; ex) a user template directory
+    /// customizing a few files of a built-in set
+    ///
+    pub fn overlay(&mut self, other: TemplateSet) {
+        self.dir = other.dir;
+        self.files.extend(other.files);
+    }
+
+    /// write the files into the directory for customization; each file keeps its line endings
+    ///
+    pub fn export(&self, dir: &Path) -> Result<()> {
+        for (name, file) in &self.files {
+            let path = dir.join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("failed to create folder `{}`", parent.display()))?;
+            }
+            let format = TextFormat {
+                line_ending: file.line_ending,
+                ..TextFormat::default()
+            };
+            write_text(&path, &file.text, &format)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::TemplateDir;
+    use crate::parser::{Parser, ParserOptions};
+
+    #[test]
+    fn test_template_set() {
+        assert_eq!(TemplateSet::builtin_names(), ["gtest", "unity"]);
+        let mut set = TemplateSet::builtin("gtest").unwrap();
+        assert!(set.files.contains_key(".vscode/tasks.json.njk"));
+        assert_eq!(
+            set.files["CMakeLists.txt.njk"].path,
+            Path::new("<builtin>/gtest/CMakeLists.txt.njk")
+        );
+        let mut user = TemplateSet {
+            dir: PathBuf::from("templates"),
+            files: BTreeMap::new(),
+        };
+        let file = TemplateFile {
+            path: PathBuf::from("templates/CMakeLists.txt.njk"),
+            text: "custom".to_string(),
+            line_ending: LineEnding::Lf,
+        };
+        user.files.insert("CMakeLists.txt.njk".to_string(), file);
+        let count = set.files.len();
+        set.overlay(user);
+        assert_eq!(set.files.len(), count);
+        assert_eq!(set.files["CMakeLists.txt.njk"].text, "custom");
+        assert!(TemplateSet::builtin("unknown").is_err());
+
+        // exported files are loaded back as they are built in
+        let builtin = TemplateSet::builtin("gtest").unwrap();
+        assert_eq!(
+            builtin.files["CMakeLists.txt.njk"].line_ending,
+            LineEnding::CrLf
+        );
+        assert!(!builtin.files["CMakeLists.txt.njk"].text.contains('\r'));
+        let dir = std::env::temp_dir().join(format!("cyagen-export-{}", std::process::id()));
+        builtin.export(&dir).unwrap();
+        let exported = TemplateSet::load(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        for (name, file) in &builtin.files {
+            assert_eq!(exported.files[name].text, file.text, "{}", name);
+            assert_eq!(
+                exported.files[name].line_ending, file.line_ending,
+                "{}",
+                name
+            );
+        }
+
+        // every built-in template renders with a parsed source
+        let code = read_text(Path::new("example/source/sample.c"), TextEncoding::Auto).unwrap();
+        let parser = Parser::parse(&code.text);
+        for name in TemplateSet::builtin_names() {
+            let set = TemplateSet::builtin(name).unwrap();
+            let templates = TemplateDir::from_set(&set).unwrap();
+            assert!(templates.errors().is_empty());
+            for temp in set.files.keys().filter(|t| !TemplateDir::is_partial(t)) {
+                let rendered = templates.render(temp, &parser, &tera::Context::new());
+                assert!(rendered.unwrap().is_some(), "{}/{}", name, temp);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unity_local_statics() {
+        // functions may have local static variables of the same name, and macros have their own layouts
+        let code = "\
+#include <stdint.h>
+#if !defined(LOCAL_STATIC_VARIABLE)
+# define LOCAL_STATIC_VARIABLE(funcname, datatype, varname, initvalue) static datatype varname = initvalue
+#endif
+#if !defined(TESTABLE_STATIC)
+# define TESTABLE_STATIC(datatype, varname, initvalue) static datatype varname = initvalue
+#endif
+int up(void)
+{
+    LOCAL_STATIC_VARIABLE(up, int, calls, 0);
+    return ++calls;
+}
+int down(void)
+{
+    LOCAL_STATIC_VARIABLE(down, uint8_t, calls, 10U);
+    return --calls;
+}
+int tick(void)
+{
+    static int plain = 0;
+    TESTABLE_STATIC(uint16_t, ticks, 0U);
+    return ++ticks + plain;
+}
+";
+        let options = ParserOptions {
+            lsv_macros: vec![
+                "LOCAL_STATIC_VARIABLE".parse().unwrap(),
+                "TESTABLE_STATIC(type, name, init)".parse().unwrap(),
+            ],
+            ..Default::default()
+        };
+        let mut parser = Parser::parse_with_options(code, &options);
+        parser.sourcename = "counter".to_string();
+        let set = TemplateSet::builtin("unity").unwrap();
+        let templates = TemplateDir::from_set(&set).unwrap();
+        let render = |name: &str| {
+            templates
+                .render(name, &parser, &tera::Context::new())
+                .unwrap()
+                .unwrap()
+                .text
+        };
+        let text = render("test_@sourcename@.c.njk");
+        assert!(text.contains("#define TESTABLE_STATIC(datatype, varname, initvalue)"));
+        assert!(text.contains("#define _lsv_of_ticks _tick_ticks"));
+        assert_eq!(text.matches("#define calls (*_lsv_calls)").count(), 1);
+        assert_eq!(text.matches("#undef calls").count(), 1);
+        assert!(text.contains("uint8_t _down_calls = 10U;"));
+        assert!(!text.contains("_tick_plain"));
+
+        // the rendered test compiles with the source and its mocks; skipped without a C compiler
+        let dir = std::env::temp_dir().join(format!("cyagen-unity-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("counter.c"), code).unwrap();
+        fs::write(dir.join("test_counter.c"), &text).unwrap();
+        fs::write(
+            dir.join("mock_counter.h"),
+            render("mock_@sourcename@.h.njk"),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("unity.h"),
+            "#define TEST_IGNORE_MESSAGE(message) ((void)(message))\n",
+        )
+        .unwrap();
+        let status = std::process::Command::new("cc")
+            .args(["-fsyntax-only", "-Wall", "-Werror", "-I"])
+            .arg(&dir)
+            .arg(dir.join("test_counter.c"))
+            .status();
+        fs::remove_dir_all(&dir).unwrap();
+        match status {
+            Ok(status) => assert!(status.success()),
+            Err(error) => eprintln!("skipped to compile the unity test: {}", error),
+        }
+    }
+}